@@ -0,0 +1,352 @@
+/// Rule based classification of documents so mixed mail scans
+/// can be routed to the right extractor
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+use crate::{
+    err::OcrResult,
+    pdf::doc::PdfDoc,
+    search::pattern::Pattern,
+    server::docling::{PageSize, ParsedDoc},
+};
+
+/// Type of a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocKind {
+    Invoice,
+    Statement,
+    Receipt,
+    CreditMemo,
+    /// IRS form W-9
+    W9,
+    Check,
+    /// guest registration and credit card authorization forms
+    GuestForm,
+    Unknown,
+}
+
+impl DocKind {
+    /// true if the invoice extractor understands the document
+    pub fn has_invoice_details(&self) -> bool {
+        matches!(
+            self,
+            DocKind::Invoice | DocKind::Receipt | DocKind::CreditMemo
+        )
+    }
+}
+
+/// What a rule looks for
+#[derive(Debug, Clone)]
+pub enum Condition {
+    /// the pattern matches anywhere in the document
+    Text(Regex),
+    /// the pattern matches a text in the top `fraction` of the first page
+    Header(Regex, f64),
+    /// the pattern matches a title or a section header
+    Title(Regex),
+    /// at least that many key value pairs were found
+    MinKeyValues(usize),
+    /// the document has at most that many pages
+    MaxPages(usize),
+}
+
+/// patterns are matched case insensitively on word boundaries
+fn compile(pattern: Pattern) -> OcrResult<Regex> {
+    Ok(RegexBuilder::new(&pattern.source(true))
+        .case_insensitive(true)
+        .build()?)
+}
+
+impl Condition {
+    pub fn text<P: Into<Pattern>>(pattern: P) -> OcrResult<Self> {
+        Ok(Self::Text(compile(pattern.into())?))
+    }
+
+    pub fn header<P: Into<Pattern>>(pattern: P, fraction: f64) -> OcrResult<Self> {
+        Ok(Self::Header(compile(pattern.into())?, fraction))
+    }
+
+    pub fn title<P: Into<Pattern>>(pattern: P) -> OcrResult<Self> {
+        Ok(Self::Title(compile(pattern.into())?))
+    }
+
+    /// `None` if the condition needs the layout and only the text is known
+    fn eval(&self, text: &str, doc: Option<&ParsedDoc>) -> Option<bool> {
+        let matched = match self {
+            Condition::Text(re) => re.is_match(text),
+            Condition::Header(re, fraction) => header(doc?, *fraction).any(|t| re.is_match(t)),
+            Condition::Title(re) => doc?
+                .texts
+                .iter()
+                .filter(|t| matches!(t.label.as_deref(), Some("title" | "section_header")))
+                .any(|t| re.is_match(&t.text)),
+            Condition::MinKeyValues(n) => doc?.key_values(&Default::default()).len() >= *n,
+            Condition::MaxPages(n) => doc?.page_count() <= *n,
+        };
+        Some(matched)
+    }
+}
+
+/// texts of the first page starting in the top `fraction` of it
+fn header(doc: &ParsedDoc, fraction: f64) -> impl Iterator<Item = &str> {
+    let first = doc
+        .texts
+        .iter()
+        .flat_map(|t| t.prov.iter())
+        .map(|p| p.page_no)
+        .min();
+    let size = first.and_then(|n| doc.pages.get(&n)).map(|p| p.size);
+
+    doc.texts.iter().filter_map(move |t| {
+        let prov = t.prov.iter().find(|p| Some(p.page_no) == first)?;
+        let PageSize { height, .. } = size?;
+        let bbox = prov.bbox.to_top_left(height);
+        (bbox.t.min(bbox.b) <= height * fraction).then_some(t.text.as_str())
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub kind: DocKind,
+    pub condition: Condition,
+    /// added to the score of the kind when the condition holds,
+    /// negative weights count against it
+    pub weight: f64,
+}
+
+/// Result of the classification
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Classification {
+    /// best kind, `Unknown` if no kind reached the minimum score
+    pub kind: DocKind,
+    /// 0.0 - 1.0, share of the weight of the rules of the kind that matched
+    pub score: f64,
+    /// score of every kind that matched at least one rule, best first
+    pub scores: Vec<(DocKind, f64)>,
+}
+
+/// Classifies documents using weighted keyword and layout rules
+#[derive(Debug, Clone)]
+pub struct Classifier {
+    pub rules: Vec<Rule>,
+    /// below this score the document is `DocKind::Unknown`
+    pub min_score: f64,
+}
+
+impl Default for Classifier {
+    /// rules for the documents found in our mail
+    fn default() -> Self {
+        default_rules().expect("invalid default classification rule")
+    }
+}
+
+fn default_rules() -> OcrResult<Classifier> {
+    use Condition as C;
+    use DocKind::*;
+
+    let text = |s: &str| C::text(s);
+    let re = |s: &str| C::text(Pattern::regex(s));
+    let head = |s: &str| C::header(s, 0.3);
+
+    Ok(Classifier::new()
+        .rule(Invoice, text("invoice")?, 2.0)
+        .rule(Invoice, re(r"invoice\s*(?:no|number|#|date)")?, 2.0)
+        .rule(Invoice, head("invoice")?, 2.0)
+        .rule(Invoice, text("bill to")?, 1.0)
+        .rule(Invoice, re(r"(?:amount|balance|total) due")?, 1.0)
+        .rule(Invoice, text("due date")?, 1.0)
+        .rule(Invoice, re(r"credit (?:memo|note)")?, -4.0)
+        .rule(Invoice, text("statement of account")?, -2.0)
+        .rule(Statement, head("statement")?, 3.0)
+        .rule(Statement, text("statement of account")?, 3.0)
+        .rule(
+            Statement,
+            re(r"(?:opening|previous|beginning) balance")?,
+            1.0,
+        )
+        .rule(Statement, re(r"(?:closing|ending) balance")?, 1.0)
+        .rule(Statement, re(r"over 90|90\+ days|aging")?, 1.0)
+        .rule(Receipt, head("receipt")?, 3.0)
+        .rule(
+            Receipt,
+            re(r"thank you for (?:your )?(?:purchase|shopping)")?,
+            1.0,
+        )
+        .rule(
+            Receipt,
+            re(r"change due|cash tendered|amount tendered")?,
+            1.0,
+        )
+        .rule(Receipt, text("paid")?, 0.5)
+        .rule(Receipt, C::MaxPages(1), 0.5)
+        .rule(CreditMemo, re(r"credit (?:memo|note)")?, 4.0)
+        .rule(CreditMemo, head("credit")?, 2.0)
+        .rule(CreditMemo, re(r"credit (?:amount|total)")?, 1.0)
+        .rule(W9, text("form w-9")?, 3.0)
+        .rule(W9, text("request for taxpayer identification number")?, 3.0)
+        .rule(W9, text("taxpayer identification number")?, 1.0)
+        .rule(W9, re(r"w-?9")?, 1.0)
+        .rule(Check, text("pay to the order of")?, 4.0)
+        .rule(Check, text("dollars")?, 1.0)
+        .rule(Check, re(r"void after \d+ days")?, 1.0)
+        .rule(Check, text("memo")?, 0.5)
+        .rule(GuestForm, text("credit card authorization")?, 3.0)
+        .rule(GuestForm, text("guest")?, 1.0)
+        .rule(GuestForm, text("cardholder")?, 1.0)
+        .rule(GuestForm, re(r"registration|check-in|arrival")?, 1.0)
+        .rule(GuestForm, text("signature")?, 0.5))
+}
+
+impl Classifier {
+    /// a classifier without any rule
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            min_score: 0.3,
+        }
+    }
+
+    pub fn rule(mut self, kind: DocKind, condition: Condition, weight: f64) -> Self {
+        self.rules.push(Rule {
+            kind,
+            condition,
+            weight,
+        });
+        self
+    }
+
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// classifies the text, `doc` is used by the layout rules.
+    /// rules that cannot be evaluated are left out of the score
+    fn classify_with(&self, text: &str, doc: Option<&ParsedDoc>) -> Classification {
+        let mut kinds: Vec<(DocKind, f64, f64)> = Vec::new();
+        for rule in &self.rules {
+            let Some(matched) = rule.condition.eval(text, doc) else {
+                continue;
+            };
+
+            let idx = match kinds.iter().position(|(k, ..)| *k == rule.kind) {
+                Some(idx) => idx,
+                None => {
+                    kinds.push((rule.kind, 0.0, 0.0));
+                    kinds.len() - 1
+                }
+            };
+            let (_, sum, total) = &mut kinds[idx];
+            if rule.weight > 0.0 {
+                *total += rule.weight;
+            }
+            if matched {
+                *sum += rule.weight;
+            }
+        }
+
+        let mut scores: Vec<(DocKind, f64)> = kinds
+            .into_iter()
+            .filter(|(_, sum, total)| *sum > 0.0 && *total > 0.0)
+            .map(|(kind, sum, total)| (kind, (sum / total).min(1.0)))
+            .collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let (kind, score) = scores
+            .first()
+            .copied()
+            .filter(|(_, s)| *s >= self.min_score)
+            .unwrap_or((DocKind::Unknown, 0.0));
+
+        Classification {
+            kind,
+            score,
+            scores,
+        }
+    }
+
+    pub fn classify(&self, doc: &ParsedDoc) -> Classification {
+        let text = doc
+            .texts
+            .iter()
+            .map(|t| t.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.classify_with(&text, Some(doc))
+    }
+
+    /// classifies plain text, layout rules are skipped
+    pub fn classify_text(&self, text: &str) -> Classification {
+        self.classify_with(text, None)
+    }
+}
+
+impl PdfDoc {
+    /// Classifies the document using its ocred images and its text layer.
+    /// image only PDFs must be ocred first
+    pub fn classify(&self, classifier: &Classifier) -> OcrResult<Classification> {
+        let merged = self.merged_doc();
+
+        let doc = self.load()?;
+        let mut text = Vec::new();
+        for page in doc.pages().iter() {
+            text.push(page.text()?.all());
+        }
+        text.extend(merged.texts.iter().map(|t| t.text.clone()));
+
+        let layout = (!merged.texts.is_empty()).then_some(&merged);
+        Ok(classifier.classify_with(&text.join("\n"), layout))
+    }
+}
+
+#[test]
+fn classify() {
+    let doc = |texts: serde_json::Value| -> ParsedDoc {
+        serde_json::from_value(serde_json::json!({
+            "texts": texts,
+            "pages": { "1": { "page_no": 1, "size": { "width": 100.0, "height": 100.0 } } }
+        }))
+        .unwrap()
+    };
+    let text = |text: &str, t: f64| serde_json::json!({ "text": text, "prov": [{ "page_no": 1, "charspan": [0, 1], "bbox": { "l": 0.0, "t": t, "r": 50.0, "b": t + 5.0, "coord_origin": "TOPLEFT" } }] });
+    let classifier = Classifier::default();
+
+    let invoice = doc(serde_json::json!([
+        text("INVOICE", 5.0),
+        text("Invoice No: 1042", 15.0),
+        text("Bill To: Golden Waffles", 30.0),
+        text("Amount Due $28.00", 80.0),
+    ]));
+    let found = classifier.classify(&invoice);
+    assert_eq!(found.kind, DocKind::Invoice);
+    assert!(found.score > 0.8);
+
+    let memo = doc(serde_json::json!([
+        text("CREDIT MEMO", 5.0),
+        text("Original invoice No: 1042", 15.0),
+        text("Credit amount $28.00", 30.0),
+    ]));
+    assert_eq!(classifier.classify(&memo).kind, DocKind::CreditMemo);
+
+    let check =
+        classifier.classify_text("PAY TO THE ORDER OF Golden Waffles $28.00 Twenty eight dollars");
+    assert_eq!(check.kind, DocKind::Check);
+    // "#" followed by a space or ":" has no word boundary after it
+    let rule = Condition::text(Pattern::regex(r"invoice\s*(?:no|number|#|date)")).unwrap();
+    for text in ["Invoice # 1042", "Invoice #: 1042", "INVOICE #1042"] {
+        assert_eq!(rule.eval(text, None), Some(true), "{text}");
+    }
+    assert_eq!(rule.eval("reinvoice #1042", None), Some(false));
+    assert_eq!(
+        classifier
+            .classify_text("Invoice # 1042 Bill To Golden Waffles")
+            .kind,
+        DocKind::Invoice
+    );
+
+    assert_eq!(
+        classifier.classify_text("lorem ipsum").kind,
+        DocKind::Unknown
+    );
+}
@@ -0,0 +1,244 @@
+/// Parsing of the dates printed on invoices in the many formats vendors use.
+/// Month names are read in English and Spanish, numeric dates are
+/// read in the day/month order of the vendor
+use std::sync::LazyLock;
+
+use chrono::{Datelike, NaiveDate};
+use regex::{Captures, Regex};
+use serde::Serialize;
+
+use crate::lang::Language;
+
+/// Order of the day and the month in numeric dates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateOrder {
+    /// `1/5/2024` is January 5
+    #[default]
+    MonthFirst,
+    /// `1/5/2024` is May 1
+    DayFirst,
+    /// `2024/1/5` is January 5
+    YearFirst,
+}
+
+impl DateOrder {
+    /// the order used for dates in the language
+    pub fn for_language(lang: Language) -> Self {
+        match lang {
+            Language::English => DateOrder::MonthFirst,
+            Language::Chinese | Language::Japanese | Language::Korean => DateOrder::YearFirst,
+            _ => DateOrder::DayFirst,
+        }
+    }
+}
+
+/// A date found in a text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DateMatch {
+    pub date: NaiveDate,
+    /// the `chrono` format that matched
+    pub format: String,
+}
+
+/// Finds dates in texts by trying a list of `chrono` formats in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateParser {
+    pub formats: Vec<String>,
+    /// years outside of this range are rejected,
+    /// so `1/5/24` is not read as the year 24
+    pub years: (i32, i32),
+}
+
+impl Default for DateParser {
+    fn default() -> Self {
+        Self::new(DateOrder::default())
+    }
+}
+
+/// the numeric formats in the order, `%Y` before `%y`
+fn numeric(order: DateOrder) -> impl Iterator<Item = String> {
+    ["/", "-", "."].into_iter().flat_map(move |sep| {
+        ["%Y", "%y"].into_iter().map(move |year| match order {
+            DateOrder::MonthFirst => format!("%m{sep}%d{sep}{year}"),
+            DateOrder::DayFirst => format!("%d{sep}%m{sep}{year}"),
+            DateOrder::YearFirst => format!("{year}{sep}%m{sep}%d"),
+        })
+    })
+}
+
+impl DateParser {
+    /// The common formats: ISO dates, dates with month names and numeric
+    /// dates in the order. numeric dates that are invalid in the order,
+    /// i.e. `25/01/2024` for `MonthFirst`, are tried in the other order,
+    /// month first for `YearFirst`
+    pub fn new(order: DateOrder) -> Self {
+        let other = match order {
+            DateOrder::MonthFirst => DateOrder::DayFirst,
+            DateOrder::DayFirst | DateOrder::YearFirst => DateOrder::MonthFirst,
+        };
+
+        let formats = [
+            "%Y-%m-%d",
+            "%Y/%m/%d",
+            "%Y.%m.%d",
+            "%b %d, %Y",
+            "%b %d %Y",
+            "%d %b %Y",
+            "%d %b, %Y",
+            "%d-%b-%Y",
+            "%d-%b-%y",
+            "%d/%b/%Y",
+            "%b %d, %y",
+            "%d %b %y",
+        ]
+        .into_iter()
+        .map(String::from)
+        .chain(numeric(order))
+        .chain(numeric(other))
+        .collect();
+
+        Self {
+            formats,
+            years: (1900, 2100),
+        }
+    }
+
+    /// the common formats in the order of the language
+    pub fn for_language(lang: Language) -> Self {
+        Self::new(DateOrder::for_language(lang))
+    }
+
+    /// a parser trying only the formats
+    pub fn with_formats<I: IntoIterator<Item = S>, S: Into<String>>(formats: I) -> Self {
+        Self {
+            formats: formats.into_iter().map(Into::into).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// tries the format after the ones already known
+    pub fn format<S: Into<String>>(mut self, format: S) -> Self {
+        self.formats.push(format.into());
+        self
+    }
+
+    /// The first date of the text. at every word the formats are tried in order,
+    /// a date must not be followed by a letter or a digit
+    pub fn parse(&self, text: &str) -> Option<DateMatch> {
+        let text = normalize(text);
+        let starts = text
+            .char_indices()
+            .filter(|(i, _)| !text[..*i].ends_with(char::is_alphanumeric))
+            .map(|(i, _)| i);
+
+        for start in starts {
+            for format in &self.formats {
+                let Ok((date, rest)) = NaiveDate::parse_and_remainder(&text[start..], format)
+                else {
+                    continue;
+                };
+                if rest.starts_with(char::is_alphanumeric)
+                    || !(self.years.0..=self.years.1).contains(&date.year())
+                {
+                    continue;
+                }
+                return Some(DateMatch {
+                    date,
+                    format: format.clone(),
+                });
+            }
+        }
+        None
+    }
+}
+
+/// month names and abbreviations in English and Spanish
+const MONTHS: [(&str, &[&str]); 12] = [
+    ("Jan", &["january", "enero", "jan", "ene"]),
+    ("Feb", &["february", "febrero", "feb"]),
+    ("Mar", &["march", "marzo", "mar"]),
+    ("Apr", &["april", "abril", "apr", "abr"]),
+    ("May", &["mayo", "may"]),
+    ("Jun", &["june", "junio", "jun"]),
+    ("Jul", &["july", "julio", "jul"]),
+    ("Aug", &["august", "agosto", "aug", "ago"]),
+    (
+        "Sep",
+        &["september", "septiembre", "setiembre", "sept", "sep"],
+    ),
+    ("Oct", &["october", "octubre", "oct"]),
+    ("Nov", &["november", "noviembre", "nov"]),
+    ("Dec", &["december", "diciembre", "dec", "dic"]),
+];
+
+static MONTH: LazyLock<Regex> = LazyLock::new(|| {
+    let names: Vec<&str> = MONTHS
+        .iter()
+        .flat_map(|(_, names)| names.iter().copied())
+        .collect();
+    Regex::new(&format!(r"(?i)\b({})\b\.?", names.join("|"))).unwrap()
+});
+
+/// `5 de Jan de 2024` and `5th`
+static FILLER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(\d)(?:st|nd|rd|th)\b|\s+del?\s+((?:Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec)\b|\d)").unwrap()
+});
+
+/// writes month names as English abbreviations, which `%b` understands
+fn normalize(text: &str) -> String {
+    let text = MONTH.replace_all(text, |caps: &Captures| {
+        let name = caps[1].to_lowercase();
+        MONTHS
+            .iter()
+            .find(|(_, names)| names.contains(&name.as_str()))
+            .map_or("", |(abbr, _)| abbr)
+            .to_owned()
+    });
+    FILLER
+        .replace_all(&text, |caps: &Captures| match (caps.get(1), caps.get(2)) {
+            (Some(digit), _) => digit.as_str().to_owned(),
+            (_, Some(next)) => format!(" {}", next.as_str()),
+            _ => String::new(),
+        })
+        .into_owned()
+}
+
+#[test]
+fn parse() {
+    let us = DateParser::default();
+    let date = |parser: &DateParser, s: &str| parser.parse(s).map(|m| m.date.to_string());
+    let jan5 = Some("2024-01-05".to_owned());
+
+    for s in [
+        "2024-01-05",
+        "Jan 5, 2024",
+        "05-JAN-24",
+        "1/5/24",
+        "Invoice Date: January 5th, 2024",
+        "5 de enero de 2024",
+        "**Due Date:** approximately 01/05/2024.",
+    ] {
+        assert_eq!(date(&us, s), jan5, "{s}");
+    }
+
+    let found = us.parse("Fecha: 5-ene-24").unwrap();
+    assert_eq!(found.format, "%d-%b-%y");
+
+    let eu = DateParser::for_language(Language::Spanish);
+    assert_eq!(date(&eu, "5.1.2024"), jan5);
+    assert_eq!(date(&us, "5.1.2024"), Some("2024-05-01".into()));
+    assert_eq!(date(&us, "25/01/2024"), Some("2024-01-25".into()));
+    assert_eq!(date(&us, "Net 30"), None);
+
+    let ja = DateParser::for_language(Language::Japanese);
+    assert_eq!(
+        DateOrder::for_language(Language::Korean),
+        DateOrder::YearFirst
+    );
+    assert_eq!(date(&ja, "2024/1/5"), jan5);
+    assert_eq!(date(&ja, "24.01.05"), jan5);
+    assert_eq!(date(&ja, "01/05/2024"), jan5);
+
+    let custom = DateParser::with_formats(["%Y%m%d"]);
+    assert_eq!(date(&custom, "batch 20240105"), jan5);
+}
@@ -0,0 +1,387 @@
+/// Detection of invoices that were already processed, i.e. emailed twice
+/// or rescanned. Invoices are compared by their normalized vendor, number,
+/// date and total and by perceptual hashes of their pages
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use image::{DynamicImage, imageops::FilterType};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    OcrEngine,
+    err::OcrResult,
+    money::Money,
+    pdf::doc::{PdfDoc, PdfInvoiceDoc},
+    persist::{self, Versioned},
+    server::invoice::InvoiceDetails,
+    vendor::normalize_name,
+};
+
+/// 64 bit difference hash of an image, similar images
+/// have hashes that differ in few bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DHash(pub u64);
+
+impl DHash {
+    /// compares the brightness of neighbouring pixels of the image shrunk to 9x8
+    pub fn of(img: &DynamicImage) -> Self {
+        let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+        let mut bits = 0;
+        for y in 0..8 {
+            for x in 0..8 {
+                bits <<= 1;
+                if small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0] {
+                    bits |= 1;
+                }
+            }
+        }
+        DHash(bits)
+    }
+
+    /// number of bits that differ
+    pub fn distance(&self, other: &DHash) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+}
+
+/// FNV-1a hash of the bytes in hex, stable across runs so it can be stored
+pub fn content_id(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+impl PdfDoc {
+    /// id of the file, see `content_id`
+    pub fn content_id(&self) -> String {
+        content_id(&self.bytes)
+    }
+
+    /// hashes of the rendered pages
+    pub fn page_hashes(&self) -> OcrResult<Vec<DHash>> {
+        let pages = self.load()?.pages().len();
+        (0..pages)
+            .map(|i| Ok(DHash::of(&self.render_page(i)?)))
+            .collect()
+    }
+}
+
+/// The fields an invoice is identified by, normalized so
+/// `SYSCO CORP.` and `Sysco`, `INV-001042` and `inv 1042` are the same
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvoiceKey {
+    pub vendor: Option<String>,
+    pub inv_no: Option<String>,
+    pub inv_date: Option<NaiveDate>,
+    pub total: Option<Money>,
+}
+
+/// letters and digits in upper case, leading zeros of numbers removed
+fn normalize_inv_no(inv_no: &str) -> Option<String> {
+    let mut out = String::new();
+    for c in inv_no.chars().filter(char::is_ascii_alphanumeric) {
+        let leading_zero = c == '0' && !out.ends_with(|c: char| c.is_ascii_digit());
+        if !leading_zero {
+            out.push(c.to_ascii_uppercase());
+        }
+    }
+    (!out.is_empty()).then_some(out)
+}
+
+impl InvoiceKey {
+    pub fn new(details: &InvoiceDetails) -> Self {
+        Self {
+            vendor: details
+                .vendor
+                .as_deref()
+                .map(normalize_name)
+                .filter(|v| !v.is_empty()),
+            inv_no: details.inv_no.as_deref().and_then(normalize_inv_no),
+            inv_date: details.inv_date.as_ref().left().copied(),
+            total: details.total.as_ref().left().map(Money::round),
+        }
+    }
+
+    /// identifies the vendor by its id instead of its name,
+    /// see `vendor::VendorMaster::match_invoice`
+    pub fn with_vendor<S: Into<String>>(mut self, vendor: S) -> Self {
+        self.vendor = Some(vendor.into());
+        self
+    }
+}
+
+/// An invoice that was processed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvoiceRecord {
+    /// file name of the document, or its `PdfDoc::content_id` if it has none
+    pub reference: String,
+    pub key: InvoiceKey,
+    pub pages: Vec<DHash>,
+    pub added: DateTime<Utc>,
+}
+
+/// Why an invoice is a likely duplicate, strongest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateReason {
+    /// same vendor, number, date and total
+    Exact,
+    /// same vendor and number, the date or total were read differently
+    SameNumber,
+    /// the pages look alike and the number, or the total and date, are the same.
+    /// invoices printed on the same template look alike, so the pages alone do not count,
+    /// nor does the vendor as recurring bills share it with their total
+    SimilarPages,
+    /// same vendor, date and total under another number
+    SameAmount,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateMatch {
+    /// the earlier invoice
+    pub record: InvoiceRecord,
+    pub reason: DuplicateReason,
+    /// largest distance between the hashes of the pages, if they were compared
+    pub page_distance: Option<u32>,
+}
+
+impl Versioned for Vec<InvoiceRecord> {
+    const KIND: &'static str = "invoice_records";
+    const VERSION: u32 = 1;
+
+    fn from_unversioned(data: Value) -> OcrResult<Self> {
+        Ok(serde_json::from_value(data)?)
+    }
+}
+
+/// Invoices processed so far, in memory or saved to a JSON file
+#[derive(Debug, Clone)]
+pub struct DuplicateStore {
+    pub records: Vec<InvoiceRecord>,
+    /// pages whose hashes differ in more bits are different
+    pub max_distance: u32,
+    path: Option<PathBuf>,
+}
+
+impl Default for DuplicateStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// true if both are known and equal
+fn same<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+    a.is_some() && a == b
+}
+
+impl DuplicateStore {
+    /// a store kept in memory
+    pub fn new() -> Self {
+        Self {
+            records: Vec::new(),
+            max_distance: 8,
+            path: None,
+        }
+    }
+
+    /// a store saved to the file after every record, loaded if the file exists
+    pub fn open<P: AsRef<Path>>(path: P) -> OcrResult<Self> {
+        let path = path.as_ref();
+        let records = match path.exists() {
+            true => persist::load(path)?,
+            false => Vec::new(),
+        };
+        Ok(Self {
+            records,
+            path: Some(path.to_owned()),
+            ..Self::new()
+        })
+    }
+
+    pub fn max_distance(mut self, max_distance: u32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// largest distance between the pages, `None` unless both have the same number of pages
+    fn page_distance(a: &[DHash], b: &[DHash]) -> Option<u32> {
+        (!a.is_empty() && a.len() == b.len())
+            .then(|| a.iter().zip(b).map(|(a, b)| a.distance(b)).max())
+            .flatten()
+    }
+
+    /// The strongest match among the records, the earliest one if several are as strong
+    pub fn find(&self, key: &InvoiceKey, pages: &[DHash]) -> Option<DuplicateMatch> {
+        let mut best: Option<DuplicateMatch> = None;
+        for record in &self.records {
+            let other = &record.key;
+            let vendor = same(&key.vendor, &other.vendor);
+            let number = same(&key.inv_no, &other.inv_no);
+            let date = same(&key.inv_date, &other.inv_date);
+            let total = same(&key.total, &other.total);
+            let page_distance = Self::page_distance(pages, &record.pages);
+            let similar = page_distance.is_some_and(|d| d <= self.max_distance);
+
+            let reason = if vendor && number && date && total {
+                DuplicateReason::Exact
+            } else if vendor && number {
+                DuplicateReason::SameNumber
+            } else if similar && (number || (total && date)) {
+                DuplicateReason::SimilarPages
+            } else if vendor && date && total {
+                DuplicateReason::SameAmount
+            } else {
+                continue;
+            };
+
+            if best.as_ref().is_none_or(|b| reason < b.reason) {
+                best = Some(DuplicateMatch {
+                    record: record.clone(),
+                    reason,
+                    page_distance,
+                });
+            }
+        }
+        best
+    }
+
+    /// records the invoice, saving the store if it has a file
+    pub fn add(&mut self, record: InvoiceRecord) -> OcrResult<()> {
+        self.records.push(record);
+        match &self.path {
+            Some(path) => persist::save(path, &self.records),
+            None => Ok(()),
+        }
+    }
+
+    /// looks for an earlier copy of the invoice then records it
+    pub fn check<S: Into<String>>(
+        &mut self,
+        reference: S,
+        key: InvoiceKey,
+        pages: Vec<DHash>,
+    ) -> OcrResult<Option<DuplicateMatch>> {
+        let found = self.find(&key, &pages);
+        self.add(InvoiceRecord {
+            reference: reference.into(),
+            key,
+            pages,
+            added: Utc::now(),
+        })?;
+        Ok(found)
+    }
+}
+
+impl PdfInvoiceDoc {
+    /// the file name of the document, its content id if it has none
+    /// as `OcrEngine::pdf_invoice` receives bytes only
+    pub fn reference(&self) -> String {
+        match &self.doc.name {
+            Some(name) => name.clone(),
+            None => self.doc.content_id(),
+        }
+    }
+
+    /// the details compared with the earlier invoices,
+    /// empty if they could not be read so only the pages are compared
+    fn duplicate_key(&self) -> InvoiceKey {
+        self.invoice_details
+            .as_ref()
+            .map(InvoiceKey::new)
+            .unwrap_or_default()
+    }
+
+    /// Flags the invoice if the store holds an earlier copy of it and records it,
+    /// a failure is kept in `Self::duplicate` as the invoice itself is still good
+    pub fn check_duplicate(&mut self, store: &mut DuplicateStore) {
+        self.duplicate = self
+            .doc
+            .page_hashes()
+            .and_then(|pages| store.check(self.reference(), self.duplicate_key(), pages));
+    }
+}
+
+impl OcrEngine {
+    /// flags the invoices of `Self::pdf_invoice` and `Self::route` found in the store
+    pub fn with_duplicates(mut self, store: DuplicateStore) -> Self {
+        self.duplicates = Some(Mutex::new(store));
+        self
+    }
+
+    /// same as `PdfInvoiceDoc::check_duplicate`, the pages are
+    /// rendered and hashed before the store is locked
+    pub(crate) fn flag_duplicate(&self, doc: &mut PdfInvoiceDoc) {
+        let Some(store) = &self.duplicates else {
+            return;
+        };
+        let pages = match doc.doc.page_hashes() {
+            Ok(pages) => pages,
+            Err(e) => {
+                doc.duplicate = Err(e);
+                return;
+            }
+        };
+        let mut store = store.lock().unwrap_or_else(|e| e.into_inner());
+        doc.duplicate = store.check(doc.reference(), doc.duplicate_key(), pages);
+    }
+}
+
+#[test]
+fn duplicates() {
+    let gradient = |shift: u32| {
+        DynamicImage::ImageLuma8(image::GrayImage::from_fn(90, 80, |x, y| {
+            image::Luma([((x * 3 + y * shift) % 256) as u8])
+        }))
+    };
+    let a = DHash::of(&gradient(1));
+    assert_eq!(a.distance(&DHash::of(&gradient(1))), 0);
+    assert!(a.distance(&DHash(!a.0)) == 64);
+
+    let dated = |inv_no: &str, total: &str, date: &str| {
+        let res: crate::server::invoice::InvoiceResponse =
+            serde_json::from_value(serde_json::json!({
+                "invoice_no": inv_no, "vendor": "SYSCO CORP.", "acct_no": null,
+                "inv_date": date, "due_date": null, "total": total,
+            }))
+            .unwrap();
+        InvoiceKey::new(&InvoiceDetails::from(res))
+    };
+    let details = |inv_no: &str, total: &str| dated(inv_no, total, "01/05/2024");
+
+    let path = std::env::temp_dir().join(format!("ocr-duplicates-{}.json", std::process::id()));
+    let mut store = DuplicateStore::open(&path).unwrap();
+    assert!(
+        store
+            .check("first.pdf", details("INV-001042", "$112.00"), vec![a])
+            .unwrap()
+            .is_none()
+    );
+
+    let store = DuplicateStore::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(store.records.len(), 1);
+
+    let found = store.find(&details("inv 1042", "$112.00"), &[]).unwrap();
+    assert_eq!(found.reason, DuplicateReason::Exact);
+    assert_eq!(found.record.reference, "first.pdf");
+
+    let rescan = store.find(&details("1O42", "$112.00"), &[a]).unwrap();
+    assert_eq!(rescan.reason, DuplicateReason::SimilarPages);
+    assert_eq!(rescan.page_distance, Some(0));
+
+    assert!(store.find(&details("1043", "$98.00"), &[a]).is_none());
+    // next month's bill of the same vendor and amount on the same template
+    assert!(
+        store
+            .find(&dated("1043", "$112.00", "02/05/2024"), &[a])
+            .is_none()
+    );
+
+    // unnamed documents are recorded by their content
+    assert_eq!(content_id(b"a"), "af63dc4c8601ec8c");
+}
@@ -33,6 +33,13 @@ pub enum OcrErrs {
         serde_json::Error,
     ),
 
+    #[error("Invalid search pattern")]
+    Regex(
+        #[source]
+        #[from]
+        regex::Error,
+    ),
+
     #[error("An Image Error Occurred")]
     Image(
         #[source]
@@ -47,6 +54,37 @@ pub enum OcrErrs {
         std::io::Error,
     ),
 
+    #[error("Invalid XML document")]
+    Xml(
+        #[source]
+        #[from]
+        roxmltree::Error,
+    ),
+
+    #[error("Invalid currency code: {0}")]
+    Currency(String),
+
+    #[error("Invalid vendor list: {0}")]
+    VendorList(String),
+
+    #[error("Unsupported document type: {0}")]
+    UnsupportedDoc(String),
+
+    #[error("Image {0} has no OCR result, its text cannot be redacted")]
+    NotOcred(usize),
+
+    #[error("Could not locate the {kind} redaction on page {page_no}")]
+    RedactionNotLocated { kind: String, page_no: usize },
+
+    #[error("No image found at index {0}")]
+    MissingImage(usize),
+
+    #[error("Stored {kind} uses schema version {version} which is not supported")]
+    UnsupportedVersion { kind: String, version: u32 },
+
+    #[error("Expected a stored {expected} but found {found}")]
+    KindMismatch { expected: String, found: String },
+
     #[error("An error occurred on the server")]
     Server(
         #[source]
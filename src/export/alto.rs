@@ -0,0 +1,185 @@
+use roxmltree::{Document, Node};
+
+use super::{Block, Page, Rect, Word, escape, words_rect};
+use crate::{err::OcrResult, server::docling::ParsedDoc};
+
+const NS: &str = "http://www.loc.gov/standards/alto/ns-v4#";
+
+fn pos(r: &Rect) -> String {
+    format!(
+        "HPOS=\"{}\" VPOS=\"{}\" WIDTH=\"{}\" HEIGHT=\"{}\"",
+        r.l,
+        r.t,
+        r.width(),
+        r.height()
+    )
+}
+
+/// Writes the pages as an ALTO v4 document, coordinates are in
+/// the unit of the page size. confidences are written as `WC`, 0.0 - 1.0
+pub fn write(pages: &[Page]) -> String {
+    let mut out = format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<alto xmlns=\"{}\">\n",
+            " <Description>\n",
+            "  <MeasurementUnit>pixel</MeasurementUnit>\n",
+            "  <OCRProcessing ID=\"ocr_1\"><ocrProcessingStep><processingSoftware>",
+            "<softwareName>ocr-client</softwareName>",
+            "</processingSoftware></ocrProcessingStep></OCRProcessing>\n",
+            " </Description>\n",
+            " <Layout>\n",
+        ),
+        NS
+    );
+
+    for page in pages {
+        let n = page.page_no;
+        let size = Rect {
+            l: 0.0,
+            t: 0.0,
+            r: page.width,
+            b: page.height,
+        };
+        out += &format!(
+            "  <Page ID=\"page_{n}\" PHYSICAL_IMG_NR=\"{n}\" WIDTH=\"{}\" HEIGHT=\"{}\">\n",
+            page.width, page.height
+        );
+        out += &format!("   <PrintSpace {}>\n", pos(&size));
+
+        for (i, block) in page.blocks.iter().enumerate() {
+            let id = format!("{n}_{}", i + 1);
+            let rect = pos(&block.rect);
+            out += &format!("    <TextBlock ID=\"block_{id}\" {rect}>\n");
+            out += &format!("     <TextLine ID=\"line_{id}\" {rect}>\n");
+
+            for (j, word) in block.words.iter().enumerate() {
+                if j > 0 {
+                    out += "      <SP/>\n";
+                }
+                let conf = word
+                    .confidence
+                    .map(|c| format!(" WC=\"{c}\""))
+                    .unwrap_or_default();
+                out += &format!(
+                    "      <String ID=\"string_{id}_{}\" CONTENT=\"{}\" {}{conf}/>\n",
+                    j + 1,
+                    escape(&word.text),
+                    pos(&word.rect)
+                );
+            }
+
+            out += "     </TextLine>\n    </TextBlock>\n";
+        }
+
+        out += "   </PrintSpace>\n  </Page>\n";
+    }
+
+    out += " </Layout>\n</alto>\n";
+    out
+}
+
+fn num(node: &Node, attr: &str) -> Option<f64> {
+    node.attribute(attr)?.trim().parse().ok()
+}
+
+fn rect(node: &Node) -> Option<Rect> {
+    let (l, t) = (num(node, "HPOS")?, num(node, "VPOS")?);
+    Some(Rect {
+        l,
+        t,
+        r: l + num(node, "WIDTH")?,
+        b: t + num(node, "HEIGHT")?,
+    })
+}
+
+fn named<'a, 'i>(node: Node<'a, 'i>, name: &'static str) -> impl Iterator<Item = Node<'a, 'i>> {
+    node.descendants()
+        .filter(move |n| n.tag_name().name() == name)
+}
+
+/// Reads an ALTO document of any version, every `TextLine` becomes a block
+pub fn read(alto: &str) -> OcrResult<Vec<Page>> {
+    let doc = Document::parse(alto)?;
+
+    let pages = named(doc.root(), "Page")
+        .enumerate()
+        .map(|(i, page)| {
+            let page_no = page
+                .attribute("PHYSICAL_IMG_NR")
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(i + 1);
+
+            let blocks = named(page, "TextLine")
+                .filter_map(|line| {
+                    let words: Vec<Word> = named(line, "String")
+                        .filter_map(|s| {
+                            Some(Word {
+                                text: s.attribute("CONTENT")?.to_owned(),
+                                rect: rect(&s)?,
+                                confidence: num(&s, "WC"),
+                            })
+                        })
+                        .collect();
+
+                    Some(Block {
+                        text: String::new(),
+                        rect: rect(&line).or_else(|| words_rect(&words))?,
+                        confidence: None,
+                        words,
+                    })
+                })
+                .collect();
+
+            Page {
+                page_no,
+                width: num(&page, "WIDTH").unwrap_or_default(),
+                height: num(&page, "HEIGHT").unwrap_or_default(),
+                blocks,
+            }
+        })
+        .collect();
+
+    Ok(pages)
+}
+
+impl ParsedDoc {
+    /// the document as an ALTO document, see `export::alto::write`
+    pub fn to_alto(&self) -> String {
+        write(&self.export_pages())
+    }
+
+    /// reads an ALTO document, see `export::alto::read`
+    pub fn from_alto(alto: &str) -> OcrResult<ParsedDoc> {
+        Ok(ParsedDoc::from_pages(read(alto)?))
+    }
+}
+
+#[test]
+fn round_trip() {
+    let alto = super::sample().to_alto();
+    assert!(alto.contains("CONTENT=\"&lt;USD&gt;\""));
+    assert!(alto.contains("HPOS=\"100\" VPOS=\"100\" WIDTH=\"110\" HEIGHT=\"20\""));
+    super::assert_round_trip(&ParsedDoc::from_alto(&alto).unwrap());
+
+    let v2 = r#"<?xml version="1.0" encoding="UTF-8"?>
+<alto xmlns="http://www.loc.gov/standards/alto/ns-v2#">
+  <Layout>
+    <Page ID="p1" WIDTH="2480" HEIGHT="3508" PHYSICAL_IMG_NR="1">
+      <PrintSpace>
+        <TextBlock ID="b1">
+          <TextLine ID="l1" HPOS="200" VPOS="300" WIDTH="600" HEIGHT="50">
+            <String CONTENT="Amount" HPOS="200" VPOS="300" WIDTH="280" HEIGHT="50" WC="0.9"/>
+            <SP/>
+            <String CONTENT="due" HPOS="520" VPOS="300" WIDTH="280" HEIGHT="50" WC="0.7"/>
+          </TextLine>
+        </TextBlock>
+      </PrintSpace>
+    </Page>
+  </Layout>
+</alto>"#;
+    let doc = ParsedDoc::from_alto(v2).unwrap();
+    assert_eq!(doc.texts[0].text, "Amount due");
+    assert_eq!(doc.texts[0].prov[0].bbox.r, 800.0);
+    assert!((doc.texts[0].confidence.unwrap() - 0.8).abs() < 1e-9);
+}
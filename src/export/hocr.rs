@@ -0,0 +1,219 @@
+use roxmltree::{Document, Node, ParsingOptions};
+
+use super::{Block, Page, Rect, Word, escape, words_rect};
+use crate::{err::OcrResult, server::docling::ParsedDoc};
+
+fn bbox(r: &Rect) -> String {
+    format!(
+        "bbox {} {} {} {}",
+        r.l.round(),
+        r.t.round(),
+        r.r.round(),
+        r.b.round()
+    )
+}
+
+/// Writes the pages as an hOCR (XHTML) document.
+/// confidences are written as `x_wconf` on every word, 0 - 100
+pub fn write(pages: &[Page]) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Transitional//EN\" \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd\">\n",
+        "<html xmlns=\"http://www.w3.org/1999/xhtml\" xml:lang=\"en\" lang=\"en\">\n",
+        " <head>\n",
+        "  <title></title>\n",
+        "  <meta http-equiv=\"Content-Type\" content=\"text/html;charset=utf-8\"/>\n",
+        "  <meta name=\"ocr-system\" content=\"ocr-client\"/>\n",
+        "  <meta name=\"ocr-capabilities\" content=\"ocr_page ocr_par ocr_line ocrx_word\"/>\n",
+        " </head>\n",
+        " <body>\n",
+    ));
+
+    for page in pages {
+        let n = page.page_no;
+        let size = Rect {
+            l: 0.0,
+            t: 0.0,
+            r: page.width,
+            b: page.height,
+        };
+        out += &format!(
+            "  <div class=\"ocr_page\" id=\"page_{n}\" title=\"{}; ppageno {}\">\n",
+            bbox(&size),
+            n.saturating_sub(1)
+        );
+
+        for (i, block) in page.blocks.iter().enumerate() {
+            let id = format!("{n}_{}", i + 1);
+            let rect = bbox(&block.rect);
+            out += &format!("   <p class=\"ocr_par\" id=\"par_{id}\" title=\"{rect}\">\n");
+            out += &format!("    <span class=\"ocr_line\" id=\"line_{id}\" title=\"{rect}\">");
+
+            for (j, word) in block.words.iter().enumerate() {
+                let conf = word
+                    .confidence
+                    .map(|c| format!("; x_wconf {}", (c * 100.0).round()))
+                    .unwrap_or_default();
+                out += &format!(
+                    "<span class=\"ocrx_word\" id=\"word_{id}_{}\" title=\"{}{conf}\">{}</span>",
+                    j + 1,
+                    bbox(&word.rect),
+                    escape(&word.text)
+                );
+                if j + 1 < block.words.len() {
+                    out.push(' ');
+                }
+            }
+
+            out += "</span>\n   </p>\n";
+        }
+
+        out += "  </div>\n";
+    }
+
+    out += " </body>\n</html>\n";
+    out
+}
+
+/// properties of the `title` attribute, i.e. `bbox 0 0 10 10; x_wconf 93`
+fn props<'a>(node: &Node<'a, '_>) -> impl Iterator<Item = (&'a str, &'a str)> {
+    node.attribute("title")
+        .unwrap_or_default()
+        .split(';')
+        .filter_map(|p| p.trim().split_once(' '))
+}
+
+fn prop_rect(node: &Node) -> Option<Rect> {
+    let (_, v) = props(node).find(|(k, _)| *k == "bbox")?;
+    let v: Vec<f64> = v
+        .split_whitespace()
+        .filter_map(|n| n.parse().ok())
+        .collect();
+    match v[..] {
+        [l, t, r, b] => Some(Rect { l, t, r, b }),
+        _ => None,
+    }
+}
+
+fn has_class(node: &Node, class: &str) -> bool {
+    node.attribute("class")
+        .is_some_and(|c| c.split_whitespace().any(|c| c == class))
+}
+
+/// Reads an hOCR document. every `ocr_line` becomes a block,
+/// `ocrx_word`s directly in a paragraph are read as a single line
+pub fn read(hocr: &str) -> OcrResult<Vec<Page>> {
+    let opts = ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let doc = Document::parse_with_options(hocr, opts)?;
+
+    let mut pages = Vec::new();
+    for (i, page) in doc
+        .descendants()
+        .filter(|n| has_class(n, "ocr_page"))
+        .enumerate()
+    {
+        let size = prop_rect(&page);
+        let page_no = props(&page)
+            .find(|(k, _)| *k == "ppageno")
+            .and_then(|(_, v)| v.trim().parse::<usize>().ok())
+            .map_or(i + 1, |n| n + 1);
+
+        let lines = page.descendants().filter(|n| {
+            has_class(n, "ocr_line")
+                || has_class(n, "ocrx_line")
+                || (has_class(n, "ocr_par")
+                    && !n
+                        .descendants()
+                        .any(|c| has_class(&c, "ocr_line") || has_class(&c, "ocrx_line")))
+        });
+
+        let blocks = lines
+            .filter_map(|line| {
+                let words: Vec<Word> = line
+                    .descendants()
+                    .filter(|n| has_class(n, "ocrx_word"))
+                    .filter_map(|w| {
+                        let text: String = w
+                            .descendants()
+                            .filter(|t| t.is_text())
+                            .filter_map(|t| t.text())
+                            .collect();
+                        let confidence = props(&w)
+                            .find(|(k, _)| *k == "x_wconf")
+                            .and_then(|(_, v)| v.trim().parse::<f64>().ok())
+                            .map(|c| c / 100.0);
+                        Some(Word {
+                            text: text.trim().to_owned(),
+                            rect: prop_rect(&w)?,
+                            confidence,
+                        })
+                    })
+                    .filter(|w| !w.text.is_empty())
+                    .collect();
+
+                let rect = prop_rect(&line).or_else(|| words_rect(&words))?;
+                Some(Block {
+                    text: String::new(),
+                    rect,
+                    confidence: None,
+                    words,
+                })
+            })
+            .collect();
+
+        pages.push(Page {
+            page_no,
+            width: size.map_or(0.0, |s| s.width()),
+            height: size.map_or(0.0, |s| s.height()),
+            blocks,
+        });
+    }
+
+    Ok(pages)
+}
+
+impl ParsedDoc {
+    /// the document as an hOCR document, see `export::hocr::write`
+    pub fn to_hocr(&self) -> String {
+        write(&self.export_pages())
+    }
+
+    /// reads an hOCR document, see `export::hocr::read`
+    pub fn from_hocr(hocr: &str) -> OcrResult<ParsedDoc> {
+        Ok(ParsedDoc::from_pages(read(hocr)?))
+    }
+}
+
+#[test]
+fn round_trip() {
+    let hocr = super::sample().to_hocr();
+    assert!(hocr.contains(">&lt;USD&gt;</span>"));
+    assert!(hocr.contains("title=\"bbox 100 100 210 120\""));
+    super::assert_round_trip(&ParsedDoc::from_hocr(&hocr).unwrap());
+
+    // as written by tesseract
+    let tesseract = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd">
+<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="en" lang="en">
+ <body>
+  <div class='ocr_page' id='page_1' title='image "scan.png"; bbox 0 0 1240 1754; ppageno 0; scan_res 150 150'>
+   <div class='ocr_carea' id='block_1_1' title="bbox 118 96 512 140">
+    <p class='ocr_par' id='par_1_1' lang='eng' title="bbox 118 96 512 140">
+     <span class='ocr_line' id='line_1_1' title="bbox 118 96 512 140; baseline 0 -9; x_size 44; x_descenders 9; x_ascenders 11">
+      <span class='ocrx_word' id='word_1_1' title='bbox 118 96 330 140; x_wconf 96'><strong>INVOICE</strong></span>
+      <span class='ocrx_word' id='word_1_2' title='bbox 352 96 512 140; x_wconf 90'>#42</span>
+     </span>
+    </p>
+   </div>
+  </div>
+ </body>
+</html>"#;
+    let doc = ParsedDoc::from_hocr(tesseract).unwrap();
+    assert_eq!(doc.texts.len(), 1);
+    assert_eq!(doc.texts[0].text, "INVOICE #42");
+    assert!((doc.texts[0].confidence.unwrap() - 0.93).abs() < 1e-9);
+    assert_eq!(doc.pages[&1].size.height, 1754.0);
+}
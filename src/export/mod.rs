@@ -0,0 +1,283 @@
+/// Exporters from `ParsedDoc` to the formats used by other OCR tools
+/// (hOCR, ALTO XML and PAGE XML) and importers back into a `ParsedDoc`.
+///
+/// Docling only returns a box per text, every text is written as a block
+/// holding a single line. Word boxes are estimated by splitting the line
+/// evenly between its characters
+pub mod alto;
+pub mod hocr;
+pub mod page_xml;
+
+use std::collections::BTreeMap;
+
+use crate::server::docling::{BoundingBox, OcrText, PageItem, PageSize, ParsedDoc, Prov};
+
+/// A box in top left page coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub l: f64,
+    pub t: f64,
+    pub r: f64,
+    pub b: f64,
+}
+
+impl Rect {
+    pub fn width(&self) -> f64 {
+        self.r - self.l
+    }
+
+    pub fn height(&self) -> f64 {
+        self.b - self.t
+    }
+
+    fn union(self, other: Rect) -> Rect {
+        Rect {
+            l: self.l.min(other.l),
+            t: self.t.min(other.t),
+            r: self.r.max(other.r),
+            b: self.b.max(other.b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    pub text: String,
+    pub rect: Rect,
+    pub confidence: Option<f64>,
+}
+
+/// A block of text holding a single line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub text: String,
+    pub rect: Rect,
+    pub confidence: Option<f64>,
+    pub words: Vec<Word>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    pub page_no: usize,
+    pub width: f64,
+    pub height: f64,
+    pub blocks: Vec<Block>,
+}
+
+/// splits the line into words, giving every character the same width
+fn split_words(text: &str, rect: Rect, confidence: Option<f64>) -> Vec<Word> {
+    let chars: Vec<char> = text.chars().collect();
+    let w = rect.width() / chars.len().max(1) as f64;
+
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in chars.iter().chain([&' ']).enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                words.push(Word {
+                    text: chars[s..i].iter().collect(),
+                    rect: Rect {
+                        l: rect.l + s as f64 * w,
+                        r: rect.l + i as f64 * w,
+                        ..rect
+                    },
+                    confidence,
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+impl ParsedDoc {
+    /// The document as pages of blocks in top left coordinates.
+    /// pages without a reported size are sized to fit their boxes
+    pub fn export_pages(&self) -> Vec<Page> {
+        let mut boxes: BTreeMap<usize, Vec<(&OcrText, &BoundingBox)>> = BTreeMap::new();
+        for t in &self.texts {
+            for p in &t.prov {
+                boxes.entry(p.page_no).or_default().push((t, &p.bbox));
+            }
+        }
+        for page_no in self.pages.keys() {
+            boxes.entry(*page_no).or_default();
+        }
+
+        boxes
+            .into_iter()
+            .map(|(page_no, boxes)| {
+                let size = self
+                    .pages
+                    .get(&page_no)
+                    .map(|p| p.size)
+                    .unwrap_or(PageSize {
+                        width: boxes.iter().map(|(_, b)| b.l.max(b.r)).fold(0.0, f64::max),
+                        height: boxes.iter().map(|(_, b)| b.t.max(b.b)).fold(0.0, f64::max),
+                    });
+
+                let blocks = boxes
+                    .into_iter()
+                    .filter(|(t, _)| !t.text.trim().is_empty())
+                    .map(|(t, bbox)| {
+                        let bbox = bbox.to_top_left(size.height);
+                        let rect = Rect {
+                            l: bbox.l.min(bbox.r),
+                            t: bbox.t.min(bbox.b),
+                            r: bbox.l.max(bbox.r),
+                            b: bbox.t.max(bbox.b),
+                        };
+                        Block {
+                            text: t.text.clone(),
+                            rect,
+                            confidence: t.confidence,
+                            words: split_words(&t.text, rect, t.confidence),
+                        }
+                    })
+                    .collect();
+
+                Page {
+                    page_no,
+                    width: size.width,
+                    height: size.height,
+                    blocks,
+                }
+            })
+            .collect()
+    }
+
+    /// Builds a document from imported pages, every block becomes a text.
+    /// blocks without text are joined from their words, blocks without
+    /// a confidence use the average of their words
+    pub fn from_pages<I: IntoIterator<Item = Page>>(pages: I) -> ParsedDoc {
+        let mut doc = ParsedDoc::default();
+        for page in pages {
+            doc.pages.insert(
+                page.page_no,
+                PageItem {
+                    page_no: page.page_no,
+                    size: PageSize {
+                        width: page.width,
+                        height: page.height,
+                    },
+                },
+            );
+
+            for block in page.blocks {
+                let text = match block.text.trim().is_empty() {
+                    true => block
+                        .words
+                        .iter()
+                        .map(|w| w.text.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    false => block.text,
+                };
+                let confs: Vec<f64> = block.words.iter().filter_map(|w| w.confidence).collect();
+                let confidence = block.confidence.or_else(|| {
+                    (!confs.is_empty()).then(|| confs.iter().sum::<f64>() / confs.len() as f64)
+                });
+
+                doc.texts.push(OcrText {
+                    prov: vec![Prov {
+                        page_no: page.page_no,
+                        bbox: BoundingBox {
+                            t: block.rect.t,
+                            l: block.rect.l,
+                            r: block.rect.r,
+                            b: block.rect.b,
+                            coord_origin: "TOPLEFT".to_owned(),
+                        },
+                        charspan: [0, text.chars().count()],
+                        source_img: None,
+                    }],
+                    text,
+                    label: None,
+                    confidence,
+                });
+            }
+        }
+        doc
+    }
+}
+
+/// escapes text for use in XML content and attributes
+pub(crate) fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' | '\r' | '\t' => out.push(' '),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// the box of the words, used when a block has no box of its own
+pub(crate) fn words_rect(words: &[Word]) -> Option<Rect> {
+    words.iter().map(|w| w.rect).reduce(Rect::union)
+}
+
+/// a sample document shared by the tests of the exporters
+#[cfg(test)]
+pub(crate) fn sample() -> ParsedDoc {
+    serde_json::from_value(serde_json::json!({
+        "texts": [
+            { "text": "INVOICE #42", "confidence": 0.97, "prov": [{ "page_no": 1, "charspan": [0, 11], "bbox": { "l": 100.0, "t": 1000.0, "r": 210.0, "b": 980.0, "coord_origin": "BOTTOMLEFT" } }] },
+            { "text": "Total <USD> & tax", "prov": [{ "page_no": 1, "charspan": [0, 17], "bbox": { "l": 100.0, "t": 500.0, "r": 270.0, "b": 520.0, "coord_origin": "TOPLEFT" } }] },
+            { "text": "Página 2", "confidence": 0.5, "prov": [{ "page_no": 2, "charspan": [0, 8], "bbox": { "l": 10.0, "t": 10.0, "r": 90.0, "b": 30.0, "coord_origin": "TOPLEFT" } }] },
+        ],
+        "pages": {
+            "1": { "page_no": 1, "size": { "width": 800.0, "height": 1100.0 } },
+            "2": { "page_no": 2, "size": { "width": 800.0, "height": 1100.0 } },
+        }
+    }))
+    .unwrap()
+}
+
+/// asserts that a document survived an export and import
+#[cfg(test)]
+pub(crate) fn assert_round_trip(doc: &ParsedDoc) {
+    let expected = sample().export_pages();
+    let found = doc.export_pages();
+    assert_eq!(found.len(), expected.len());
+
+    for (found, expected) in found.iter().zip(&expected) {
+        assert_eq!(found.page_no, expected.page_no);
+        assert_eq!(
+            (found.width, found.height),
+            (expected.width, expected.height)
+        );
+        assert_eq!(found.blocks.len(), expected.blocks.len());
+
+        for (f, e) in found.blocks.iter().zip(&expected.blocks) {
+            assert_eq!(f.text, e.text);
+            assert_eq!(f.rect, e.rect);
+            assert_eq!(f.confidence, e.confidence);
+        }
+    }
+}
+
+#[test]
+fn words() {
+    let rect = Rect {
+        l: 0.0,
+        t: 0.0,
+        r: 100.0,
+        b: 10.0,
+    };
+    let words = split_words("Total  due", rect, Some(0.9));
+    let found: Vec<_> = words
+        .iter()
+        .map(|w| (w.text.as_str(), w.rect.l, w.rect.r))
+        .collect();
+    assert_eq!(found, vec![("Total", 0.0, 50.0), ("due", 70.0, 100.0)]);
+}
@@ -0,0 +1,181 @@
+use chrono::{SecondsFormat, Utc};
+use roxmltree::{Document, Node};
+
+use super::{Block, Page, Rect, Word, escape, words_rect};
+use crate::{err::OcrResult, server::docling::ParsedDoc};
+
+const NS: &str = "http://schema.primaresearch.org/PAGE/gts/pagecontent/2019-07-15";
+
+/// PAGE uses integer polygons
+fn coords(r: &Rect) -> String {
+    let (l, t, r, b) = (r.l.round(), r.t.round(), r.r.round(), r.b.round());
+    format!("<Coords points=\"{l},{t} {r},{t} {r},{b} {l},{b}\"/>")
+}
+
+fn text_equiv(text: &str, conf: Option<f64>) -> String {
+    let conf = conf.map(|c| format!(" conf=\"{c}\"")).unwrap_or_default();
+    format!(
+        "<TextEquiv{conf}><Unicode>{}</Unicode></TextEquiv>",
+        escape(text)
+    )
+}
+
+/// Writes a page as a PAGE XML (2019-07-15) document.
+/// the format holds a single page, multi page documents need one file per page
+pub fn write(page: &Page) -> String {
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let n = page.page_no;
+
+    let mut out = format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<PcGts xmlns=\"{}\">\n",
+            " <Metadata>\n",
+            "  <Creator>ocr-client</Creator>\n",
+            "  <Created>{}</Created>\n",
+            "  <LastChange>{}</LastChange>\n",
+            " </Metadata>\n",
+            " <Page imageFilename=\"page_{}.png\" imageWidth=\"{}\" imageHeight=\"{}\">\n",
+        ),
+        NS,
+        now,
+        now,
+        n,
+        page.width.round(),
+        page.height.round()
+    );
+
+    for (i, block) in page.blocks.iter().enumerate() {
+        let id = format!("{n}_{}", i + 1);
+        let rect = coords(&block.rect);
+        let equiv = text_equiv(&block.text, block.confidence);
+        out += &format!("  <TextRegion id=\"r_{id}\" type=\"paragraph\">\n   {rect}\n");
+        out += &format!("   <TextLine id=\"l_{id}\">\n    {rect}\n");
+
+        for (j, word) in block.words.iter().enumerate() {
+            out += &format!(
+                "    <Word id=\"w_{id}_{}\">{}{}</Word>\n",
+                j + 1,
+                coords(&word.rect),
+                text_equiv(&word.text, word.confidence)
+            );
+        }
+
+        out += &format!("    {equiv}\n   </TextLine>\n   {equiv}\n  </TextRegion>\n");
+    }
+
+    out += " </Page>\n</PcGts>\n";
+    out
+}
+
+fn child<'a, 'i>(node: &Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|n| n.tag_name().name() == name)
+}
+
+fn rect(node: &Node) -> Option<Rect> {
+    let points = child(node, "Coords")?.attribute("points")?;
+    let points: Vec<(f64, f64)> = points
+        .split_whitespace()
+        .filter_map(|p| {
+            let (x, y) = p.split_once(',')?;
+            Some((x.parse().ok()?, y.parse().ok()?))
+        })
+        .collect();
+
+    // polygons are reduced to their bounding box
+    points
+        .iter()
+        .map(|&(x, y)| Rect {
+            l: x,
+            t: y,
+            r: x,
+            b: y,
+        })
+        .reduce(|a, b| Rect {
+            l: a.l.min(b.l),
+            t: a.t.min(b.t),
+            r: a.r.max(b.r),
+            b: a.b.max(b.b),
+        })
+}
+
+/// text and confidence of the first `TextEquiv` of the node
+fn equiv(node: &Node) -> Option<(String, Option<f64>)> {
+    let equiv = child(node, "TextEquiv")?;
+    let text = child(&equiv, "Unicode")
+        .and_then(|u| u.text())
+        .unwrap_or_default();
+    let conf = equiv.attribute("conf").and_then(|c| c.parse().ok());
+    Some((text.to_owned(), conf))
+}
+
+/// Reads a PAGE XML document of any version, every `TextLine` becomes a block
+pub fn read(page_no: usize, xml: &str) -> OcrResult<Page> {
+    let doc = Document::parse(xml)?;
+    let page = doc.descendants().find(|n| n.tag_name().name() == "Page");
+    let num = |attr| {
+        page.and_then(|p| p.attribute(attr))
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_default()
+    };
+
+    let blocks = doc
+        .descendants()
+        .filter(|n| n.tag_name().name() == "TextLine")
+        .filter_map(|line| {
+            let words: Vec<Word> = line
+                .children()
+                .filter(|n| n.tag_name().name() == "Word")
+                .filter_map(|w| {
+                    let (text, confidence) = equiv(&w)?;
+                    Some(Word {
+                        text,
+                        rect: rect(&w)?,
+                        confidence,
+                    })
+                })
+                .collect();
+            let (text, confidence) = equiv(&line).unwrap_or_default();
+
+            Some(Block {
+                text,
+                rect: rect(&line).or_else(|| words_rect(&words))?,
+                confidence,
+                words,
+            })
+        })
+        .collect();
+
+    Ok(Page {
+        page_no,
+        width: num("imageWidth"),
+        height: num("imageHeight"),
+        blocks,
+    })
+}
+
+impl ParsedDoc {
+    /// the document as PAGE XML documents, one per page
+    pub fn to_page_xml(&self) -> Vec<String> {
+        self.export_pages().iter().map(write).collect()
+    }
+
+    /// reads PAGE XML documents, one per page in order
+    pub fn from_page_xml<'a, I: IntoIterator<Item = &'a str>>(pages: I) -> OcrResult<ParsedDoc> {
+        let pages = pages
+            .into_iter()
+            .enumerate()
+            .map(|(i, xml)| read(i + 1, xml))
+            .collect::<OcrResult<Vec<_>>>()?;
+        Ok(ParsedDoc::from_pages(pages))
+    }
+}
+
+#[test]
+fn round_trip() {
+    let pages = super::sample().to_page_xml();
+    assert_eq!(pages.len(), 2);
+    assert!(pages[0].contains("<Coords points=\"100,100 210,100 210,120 100,120\"/>"));
+    assert!(pages[0].contains("<Unicode>Total &lt;USD&gt; &amp; tax</Unicode>"));
+    super::assert_round_trip(&ParsedDoc::from_page_xml(pages.iter().map(String::as_str)).unwrap());
+}
@@ -0,0 +1,362 @@
+/// Language hints for the OCR server and a
+/// local script/language detector used to fill them in
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::server::{docling::ParsedDoc, options::OcrBackend};
+
+/// Languages the OCR engines can be hinted with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    English,
+    Spanish,
+    French,
+    German,
+    Portuguese,
+    Italian,
+    Russian,
+    Arabic,
+    Chinese,
+    Japanese,
+    Korean,
+}
+
+impl Language {
+    /// ISO 639-1 code i.e. `en`
+    pub fn iso639_1(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Spanish => "es",
+            Language::French => "fr",
+            Language::German => "de",
+            Language::Portuguese => "pt",
+            Language::Italian => "it",
+            Language::Russian => "ru",
+            Language::Arabic => "ar",
+            Language::Chinese => "zh",
+            Language::Japanese => "ja",
+            Language::Korean => "ko",
+        }
+    }
+
+    /// ISO 639-2 code as used by tesseract i.e. `eng`
+    pub fn iso639_2(&self) -> &'static str {
+        match self {
+            Language::English => "eng",
+            Language::Spanish => "spa",
+            Language::French => "fra",
+            Language::German => "deu",
+            Language::Portuguese => "por",
+            Language::Italian => "ita",
+            Language::Russian => "rus",
+            Language::Arabic => "ara",
+            Language::Chinese => "chi_sim",
+            Language::Japanese => "jpn",
+            Language::Korean => "kor",
+        }
+    }
+
+    /// code understood by the given OCR engine
+    pub fn code_for(&self, engine: OcrBackend) -> &'static str {
+        match engine {
+            OcrBackend::Tesseract | OcrBackend::TesserOcr => self.iso639_2(),
+            OcrBackend::EasyOcr | OcrBackend::RapidOcr => match self {
+                Language::Chinese => "ch_sim",
+                lang => lang.iso639_1(),
+            },
+            OcrBackend::OcrMac => match self {
+                Language::English => "en-US",
+                Language::Spanish => "es-ES",
+                Language::French => "fr-FR",
+                Language::German => "de-DE",
+                Language::Portuguese => "pt-BR",
+                Language::Italian => "it-IT",
+                Language::Russian => "ru-RU",
+                Language::Arabic => "ar-SA",
+                Language::Chinese => "zh-Hans",
+                Language::Japanese => "ja-JP",
+                Language::Korean => "ko-KR",
+            },
+        }
+    }
+
+    /// parses an ISO 639-1 or 639-2 code
+    pub fn from_code(code: &str) -> Option<Self> {
+        let code = code.to_ascii_lowercase();
+        ALL.iter()
+            .copied()
+            .find(|l| l.iso639_1() == code || l.iso639_2() == code)
+    }
+}
+
+const ALL: [Language; 11] = [
+    Language::English,
+    Language::Spanish,
+    Language::French,
+    Language::German,
+    Language::Portuguese,
+    Language::Italian,
+    Language::Russian,
+    Language::Arabic,
+    Language::Chinese,
+    Language::Japanese,
+    Language::Korean,
+];
+
+/// Writing systems recognised by `script_of`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Script {
+    Latin,
+    Cyrillic,
+    Arabic,
+    Han,
+    Kana,
+    Hangul,
+    Other,
+}
+
+/// script of a single character, `None` for digits, punctuation and spaces
+pub fn script_of(c: char) -> Option<Script> {
+    if !c.is_alphabetic() {
+        return None;
+    }
+
+    let script = match c as u32 {
+        0x0041..=0x024F | 0x1E00..=0x1EFF => Script::Latin,
+        0x0400..=0x04FF => Script::Cyrillic,
+        0x0600..=0x06FF | 0x0750..=0x077F => Script::Arabic,
+        0x3040..=0x30FF => Script::Kana,
+        0x4E00..=0x9FFF | 0x3400..=0x4DBF => Script::Han,
+        0xAC00..=0xD7AF | 0x1100..=0x11FF => Script::Hangul,
+        _ => Script::Other,
+    };
+
+    Some(script)
+}
+
+/// Common words used to tell latin languages apart
+const STOPWORDS: &[(Language, &[&str])] = &[
+    (
+        Language::English,
+        &[
+            "the", "and", "of", "to", "is", "for", "with", "this", "that", "are", "from", "your",
+            "you", "by", "invoice", "amount", "due", "please", "date", "bill",
+        ],
+    ),
+    (
+        Language::Spanish,
+        &[
+            "el",
+            "los",
+            "las",
+            "del",
+            "y",
+            "que",
+            "por",
+            "para",
+            "con",
+            "una",
+            "factura",
+            "fecha",
+            "importe",
+            "pago",
+            "su",
+            "es",
+            "al",
+            "cliente",
+            "vencimiento",
+        ],
+    ),
+    (
+        Language::French,
+        &[
+            "le",
+            "les",
+            "des",
+            "du",
+            "et",
+            "est",
+            "pour",
+            "avec",
+            "une",
+            "facture",
+            "montant",
+            "votre",
+            "nous",
+            "sur",
+            "au",
+            "aux",
+            "paiement",
+            "échéance",
+        ],
+    ),
+    (
+        Language::German,
+        &[
+            "der", "die", "das", "und", "ist", "mit", "für", "von", "zu", "den", "dem", "rechnung",
+            "betrag", "bitte", "nicht", "ein", "eine", "datum",
+        ],
+    ),
+    (
+        Language::Portuguese,
+        &[
+            "os",
+            "do",
+            "da",
+            "dos",
+            "das",
+            "em",
+            "não",
+            "uma",
+            "fatura",
+            "valor",
+            "pagamento",
+            "vencimento",
+            "ao",
+            "à",
+            "você",
+        ],
+    ),
+    (
+        Language::Italian,
+        &[
+            "il",
+            "lo",
+            "gli",
+            "di",
+            "della",
+            "è",
+            "per",
+            "con",
+            "una",
+            "fattura",
+            "importo",
+            "pagamento",
+            "che",
+            "non",
+            "scadenza",
+        ],
+    ),
+];
+
+/// A detected language with the share of the text it covers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    pub lang: Language,
+    /// 0.0 - 1.0
+    pub score: f64,
+}
+
+/// Detects the languages used in the text, best first.
+/// non latin scripts are mapped to their main language,
+/// latin text is told apart by counting common words
+pub fn detect(text: &str) -> Vec<Detection> {
+    let mut scripts: HashMap<Script, usize> = HashMap::new();
+    for script in text.chars().filter_map(script_of) {
+        *scripts.entry(script).or_default() += 1;
+    }
+
+    let letters: usize = scripts.values().sum();
+    if letters == 0 {
+        return Vec::new();
+    }
+
+    let mut scores: HashMap<Language, f64> = HashMap::new();
+    let share = |script| scripts.get(&script).copied().unwrap_or(0) as f64 / letters as f64;
+
+    // kana only shows up in japanese, han alone is most likely chinese
+    let kana = share(Script::Kana);
+    let han = share(Script::Han);
+    if kana > 0.0 {
+        scores.insert(Language::Japanese, kana + han);
+    } else if han > 0.0 {
+        scores.insert(Language::Chinese, han);
+    }
+    for (script, lang) in [
+        (Script::Cyrillic, Language::Russian),
+        (Script::Arabic, Language::Arabic),
+        (Script::Hangul, Language::Korean),
+    ] {
+        if share(script) > 0.0 {
+            scores.insert(lang, share(script));
+        }
+    }
+
+    let latin = share(Script::Latin);
+    if latin > 0.0 {
+        let mut hits: HashMap<Language, usize> = HashMap::new();
+        let words = text
+            .split(|c: char| !c.is_alphabetic())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase);
+
+        for word in words {
+            for (lang, stopwords) in STOPWORDS {
+                if stopwords.contains(&word.as_str()) {
+                    *hits.entry(*lang).or_default() += 1;
+                }
+            }
+        }
+
+        let total: usize = hits.values().sum();
+        if total == 0 {
+            // nothing to go on, most of our mail is english
+            scores.insert(Language::English, latin);
+        } else {
+            for (lang, n) in hits {
+                scores.insert(lang, latin * n as f64 / total as f64);
+            }
+        }
+    }
+
+    let mut detections: Vec<_> = scores
+        .into_iter()
+        .map(|(lang, score)| Detection { lang, score })
+        .collect();
+    detections.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.lang.cmp(&b.lang)));
+    detections
+}
+
+impl ParsedDoc {
+    /// Detects the languages of the document, best first
+    pub fn detect_languages(&self) -> Vec<Detection> {
+        let text = self
+            .texts
+            .iter()
+            .map(|t| t.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        detect(&text)
+    }
+
+    /// Languages covering at least `min_score` of the document,
+    /// meant to be used as a hint when re-processing the document
+    pub fn language_hint(&self, min_score: f64) -> Vec<Language> {
+        self.detect_languages()
+            .into_iter()
+            .filter(|d| d.score >= min_score)
+            .map(|d| d.lang)
+            .collect()
+    }
+}
+
+#[test]
+fn detection() {
+    let es = detect("Factura número 123. Fecha de vencimiento: 05/01/2024. Importe total del pago");
+    assert_eq!(es[0].lang, Language::Spanish);
+
+    let fr = detect("Facture n° 42 - Montant à payer avant la date d'échéance pour votre compte");
+    assert_eq!(fr[0].lang, Language::French);
+
+    let en = detect("Please remit the amount due by the date on this invoice");
+    assert_eq!(en[0].lang, Language::English);
+
+    assert_eq!(detect("Счёт на оплату")[0].lang, Language::Russian);
+    assert_eq!(detect("請求書 お支払い")[0].lang, Language::Japanese);
+    assert!(detect("12/05/2024 $28.00").is_empty());
+
+    assert_eq!(Language::from_code("SPA"), Some(Language::Spanish));
+    assert_eq!(Language::Chinese.code_for(OcrBackend::EasyOcr), "ch_sim");
+}
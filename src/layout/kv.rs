@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+
+use crate::server::docling::{
+    BoundingBox, GraphCellLabel, GraphLinkLabel, OcrText, ParsedDoc, Prov,
+};
+
+/// Where the pairing of a key and its value came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KvSource {
+    /// "Label: value" inside a single text
+    Inline,
+    /// value sits to the right of the label
+    Right,
+    /// value sits below the label
+    Below,
+    /// key value item reported by Docling
+    Docling,
+}
+
+/// A value paired with its label
+#[derive(Debug, Clone)]
+pub struct KeyValue {
+    /// the label as it appears in the document, without the trailing colon
+    pub key: String,
+    pub value: String,
+    pub source: KvSource,
+    /// index of the label in `ParsedDoc::texts`, if it came from there
+    pub key_idx: Option<usize>,
+    /// index of the value in `ParsedDoc::texts`, if it came from there
+    pub value_idx: Option<usize>,
+    pub key_prov: Option<Prov>,
+    pub value_prov: Option<Prov>,
+}
+
+/// Controls how labels are detected and paired
+#[derive(Debug, Clone)]
+pub struct KvOptions {
+    /// labels that are recognised even without a trailing colon
+    /// e.g. "Name", "Tax ID". compared case insensitively
+    pub labels: Vec<String>,
+    /// max number of words a label may have
+    pub max_label_words: usize,
+    /// max horizontal gap between a label and a value on its right,
+    /// expressed in multiples of the label height
+    pub max_right_gap: f64,
+    /// max vertical gap between a label and a value below it,
+    /// expressed in multiples of the label height
+    pub max_below_gap: f64,
+}
+
+impl Default for KvOptions {
+    fn default() -> Self {
+        Self {
+            labels: Vec::new(),
+            max_label_words: 5,
+            max_right_gap: 20.0,
+            max_below_gap: 1.5,
+        }
+    }
+}
+
+/// normalized form of a key used in the returned map
+pub fn normalize_key(key: &str) -> String {
+    key.trim()
+        .trim_end_matches(':')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// splits "Label: value" into its parts.
+/// the value is empty if the text is a label on its own
+fn split_label<'a>(text: &'a str, opts: &KvOptions) -> Option<(&'a str, &'a str)> {
+    let text = text.trim();
+    // the colon of "https://..." does not end a label
+    let idx = text
+        .match_indices(':')
+        .map(|(i, _)| i)
+        .find(|&i| !text[i + 1..].starts_with("//"))?;
+    let (label, value) = (text[..idx].trim(), text[idx + 1..].trim());
+
+    // avoid treating times such as 10:30 as labels
+    let before = text[..idx].chars().last();
+    let after = text[idx + 1..].chars().next();
+    if before.is_some_and(|c| c.is_ascii_digit()) && after.is_some_and(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let words = label.split_whitespace().count();
+    if words == 0 || words > opts.max_label_words || !label.chars().any(char::is_alphabetic) {
+        return None;
+    }
+
+    Some((label, value))
+}
+
+fn first_prov(text: &OcrText) -> Option<&Prov> {
+    text.prov.first()
+}
+
+/// horizontal gap to a value on the right of the label
+fn right_gap(label: &BoundingBox, cand: &BoundingBox, opts: &KvOptions) -> Option<f64> {
+    let h = label.height().max(1.0);
+    let min_h = label.height().min(cand.height()).max(1.0);
+    let gap = cand.l - label.r;
+
+    (label.v_overlap(cand) >= 0.5 * min_h && gap >= -0.25 * h && gap <= opts.max_right_gap * h)
+        .then_some(gap.max(0.0))
+}
+
+/// vertical gap to a value below the label
+fn below_gap(label: &BoundingBox, cand: &BoundingBox, opts: &KvOptions) -> Option<f64> {
+    let h = label.height().max(1.0);
+    let gap = cand.top() - label.bottom();
+    let aligned = label.h_overlap(cand) > 0.0 || (cand.l - label.l).abs() <= h;
+
+    (aligned && gap >= -0.25 * h && gap <= opts.max_below_gap * h).then_some(gap.max(0.0))
+}
+
+impl ParsedDoc {
+    /// Pairs labels with their values.
+    /// Docling key value items are used first, then "Label: value" texts,
+    /// and finally labels are paired with the nearest text to their
+    /// right or below them.
+    /// The returned map is keyed by `normalize_key`, the first pair
+    /// found for a key wins
+    pub fn key_values(&self, opts: &KvOptions) -> BTreeMap<String, KeyValue> {
+        let mut map = BTreeMap::new();
+
+        for item in &self.key_value_items {
+            let cells = &item.graph.cells;
+            for link in &item.graph.links {
+                let (key_id, value_id) = match link.label {
+                    GraphLinkLabel::ToValue => (link.source_cell_id, link.target_cell_id),
+                    GraphLinkLabel::ToKey => (link.target_cell_id, link.source_cell_id),
+                    _ => continue,
+                };
+
+                let key = cells.iter().find(|c| c.cell_id == key_id);
+                let value = cells.iter().find(|c| c.cell_id == value_id);
+                if let (Some(key), Some(value)) = (key, value)
+                    && key.label != GraphCellLabel::Value
+                {
+                    map.entry(normalize_key(&key.text)).or_insert(KeyValue {
+                        key: key.text.trim().trim_end_matches(':').to_owned(),
+                        value: value.text.trim().to_owned(),
+                        source: KvSource::Docling,
+                        key_idx: None,
+                        value_idx: None,
+                        key_prov: key.prov.clone(),
+                        value_prov: value.prov.clone(),
+                    });
+                }
+            }
+        }
+
+        let known = |text: &str| {
+            let text = normalize_key(text);
+            opts.labels.iter().any(|l| normalize_key(l) == text)
+        };
+
+        // texts that are labels, inline pairs are emitted right away
+        let mut is_label = vec![false; self.texts.len()];
+        let mut labels = Vec::new();
+        for (i, text) in self.texts.iter().enumerate() {
+            match split_label(&text.text, opts) {
+                Some((label, value)) if !value.is_empty() => {
+                    is_label[i] = true;
+                    map.entry(normalize_key(label)).or_insert(KeyValue {
+                        key: label.to_owned(),
+                        value: value.to_owned(),
+                        source: KvSource::Inline,
+                        key_idx: Some(i),
+                        value_idx: Some(i),
+                        key_prov: first_prov(text).cloned(),
+                        value_prov: first_prov(text).cloned(),
+                    });
+                }
+                Some((label, _)) => {
+                    is_label[i] = true;
+                    labels.push((i, label));
+                }
+                None if known(&text.text) => {
+                    is_label[i] = true;
+                    labels.push((i, text.text.trim()));
+                }
+                None => {}
+            }
+        }
+
+        let mut used = vec![false; self.texts.len()];
+        for (i, label) in labels {
+            let Some(label_prov) = first_prov(&self.texts[i]) else {
+                continue;
+            };
+
+            let candidates = || {
+                self.texts.iter().enumerate().filter_map(|(j, t)| {
+                    let prov = first_prov(t)?;
+                    (!is_label[j]
+                        && !used[j]
+                        && prov.page_no == label_prov.page_no
+                        && !t.text.trim().is_empty())
+                    .then_some((j, prov))
+                })
+            };
+
+            let right = candidates()
+                .filter_map(|(j, p)| right_gap(&label_prov.bbox, &p.bbox, opts).map(|g| (j, g)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(j, _)| (j, KvSource::Right));
+
+            let found = right.or_else(|| {
+                candidates()
+                    .filter_map(|(j, p)| below_gap(&label_prov.bbox, &p.bbox, opts).map(|g| (j, g)))
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(j, _)| (j, KvSource::Below))
+            });
+
+            if let Some((j, source)) = found {
+                let key = normalize_key(label);
+                if map.contains_key(&key) {
+                    continue;
+                }
+
+                used[j] = true;
+                map.insert(
+                    key,
+                    KeyValue {
+                        key: label.trim_end_matches(':').trim().to_owned(),
+                        value: self.texts[j].text.trim().to_owned(),
+                        source,
+                        key_idx: Some(i),
+                        value_idx: Some(j),
+                        key_prov: Some(label_prov.clone()),
+                        value_prov: first_prov(&self.texts[j]).cloned(),
+                    },
+                );
+            }
+        }
+
+        map
+    }
+}
+
+#[test]
+fn pairs() {
+    let doc: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [
+            { "text": "Name:", "prov": [{ "page_no": 1, "charspan": [0, 5], "bbox": { "l": 10.0, "t": 10.0, "r": 60.0, "b": 22.0, "coord_origin": "TOPLEFT" } }] },
+            { "text": "Golden Waffles LLC", "prov": [{ "page_no": 1, "charspan": [0, 18], "bbox": { "l": 80.0, "t": 11.0, "r": 220.0, "b": 23.0, "coord_origin": "TOPLEFT" } }] },
+            { "text": "Tax ID", "prov": [{ "page_no": 1, "charspan": [0, 6], "bbox": { "l": 10.0, "t": 40.0, "r": 60.0, "b": 52.0, "coord_origin": "TOPLEFT" } }] },
+            { "text": "12-3456789", "prov": [{ "page_no": 1, "charspan": [0, 10], "bbox": { "l": 10.0, "t": 55.0, "r": 90.0, "b": 67.0, "coord_origin": "TOPLEFT" } }] },
+            { "text": "Phone: 555-0100", "prov": [{ "page_no": 1, "charspan": [0, 15], "bbox": { "l": 10.0, "t": 80.0, "r": 120.0, "b": 92.0, "coord_origin": "TOPLEFT" } }] },
+            { "text": "Opens at 10:30", "prov": [] },
+            { "text": "https://goldenwaffles.example/pay", "prov": [] },
+            { "text": "Web: https://goldenwaffles.example", "prov": [] }
+        ]
+    }))
+    .unwrap();
+
+    let opts = KvOptions {
+        labels: vec!["Tax ID".into()],
+        ..Default::default()
+    };
+    let kv = doc.key_values(&opts);
+
+    assert_eq!(kv["name"].value, "Golden Waffles LLC");
+    assert_eq!(kv["name"].source, KvSource::Right);
+    assert_eq!(kv["tax id"].value, "12-3456789");
+    assert_eq!(kv["tax id"].source, KvSource::Below);
+    assert_eq!(kv["phone"].value, "555-0100");
+    assert_eq!(kv["web"].value, "https://goldenwaffles.example");
+    assert!(!kv.contains_key("https"));
+    assert_eq!(kv.len(), 4);
+
+    // labels from newer Docling versions do not break parsing
+    let graph: crate::server::docling::GraphData = serde_json::from_value(serde_json::json!({
+        "cells": [{ "label": "signature", "cell_id": 0, "text": "", "prov": null }],
+        "links": [{ "label": "to_sibling", "source_cell_id": 0, "target_cell_id": 0 }]
+    }))
+    .unwrap();
+    assert_eq!(graph.cells[0].label, GraphCellLabel::Unknown);
+    assert_eq!(graph.links[0].label, GraphLinkLabel::Unknown);
+}
@@ -0,0 +1,363 @@
+use std::{collections::BTreeSet, ops::Range};
+
+use crate::server::docling::{BoundingBox, ParsedDoc};
+
+/// Thresholds used while grouping boxes,
+/// all of them are multiples of the median text height of the page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutOptions {
+    /// max distance between the baselines of two fragments on the same line
+    pub baseline_tolerance: f64,
+    /// max horizontal gap between two fragments on the same line
+    pub max_word_gap: f64,
+    /// min vertical gap between two lines that starts a new paragraph
+    pub paragraph_gap: f64,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            baseline_tolerance: 0.5,
+            max_word_gap: 3.0,
+            paragraph_gap: 0.8,
+        }
+    }
+}
+
+/// Fragments sharing a baseline, ordered left to right
+#[derive(Debug, Clone)]
+pub struct Line {
+    /// indexes into `ParsedDoc::texts`
+    pub fragments: Vec<usize>,
+    pub bbox: BoundingBox,
+}
+
+#[derive(Debug, Clone)]
+pub struct Paragraph {
+    pub lines: Vec<Line>,
+    pub bbox: BoundingBox,
+}
+
+/// A column of paragraphs.
+/// lines spanning several columns (titles, footers) get a column of their own
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub paragraphs: Vec<Paragraph>,
+    pub bbox: BoundingBox,
+}
+
+#[derive(Debug, Clone)]
+pub struct PageLayout {
+    pub page_no: usize,
+    /// columns in reading order
+    pub columns: Vec<Column>,
+}
+
+/// Maps a part of the reconstructed text back to its fragment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextSpan {
+    /// byte range in `ReadingText::text`
+    pub range: Range<usize>,
+    /// index into `ParsedDoc::texts`
+    pub text_idx: usize,
+    /// byte offset of the span within `OcrText::text`,
+    /// the leading whitespace trimmed from the fragment
+    pub offset: usize,
+}
+
+/// Text of the document in reading order.
+/// fragments are joined by spaces, lines by new lines
+/// and paragraphs, columns and pages by blank lines
+#[derive(Debug, Clone, Default)]
+pub struct ReadingText {
+    pub text: String,
+    /// sorted by `range`
+    pub spans: Vec<TextSpan>,
+}
+
+impl ReadingText {
+    /// span containing the given byte offset
+    pub fn span_at(&self, offset: usize) -> Option<&TextSpan> {
+        let idx = self.spans.partition_point(|s| s.range.end <= offset);
+        self.spans.get(idx).filter(|s| s.range.contains(&offset))
+    }
+
+    /// maps a byte range of `Self::text` to the fragments it covers
+    /// as `(text_idx, byte range within OcrText::text)`
+    pub fn source_ranges(&self, range: Range<usize>) -> Vec<(usize, Range<usize>)> {
+        self.spans
+            .iter()
+            .filter(|s| s.range.start < range.end && range.start < s.range.end)
+            .map(|s| {
+                let start = range.start.max(s.range.start) - s.range.start + s.offset;
+                let end = range.end.min(s.range.end) - s.range.start + s.offset;
+                (s.text_idx, start..end)
+            })
+            .collect()
+    }
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return 1.0;
+    }
+
+    values.sort_by(f64::total_cmp);
+    values[values.len() / 2].max(1.0)
+}
+
+fn union_all<'a>(mut boxes: impl Iterator<Item = &'a BoundingBox>) -> Option<BoundingBox> {
+    let first = boxes.next()?.clone();
+    Some(boxes.fold(first, |acc, b| acc.union(b)))
+}
+
+fn lines(frags: &[(usize, &BoundingBox)], h: f64, opts: &LayoutOptions) -> Vec<Line> {
+    let mut sorted = frags.to_vec();
+    sorted.sort_by(|a, b| a.1.top().total_cmp(&b.1.top()));
+
+    // (baseline, line)
+    let mut lines: Vec<(f64, Line)> = Vec::new();
+    for (idx, bbox) in sorted {
+        let found = lines.iter_mut().find(|(baseline, line)| {
+            let min_h = line.bbox.height().min(bbox.height()).max(1.0);
+            let gap = (bbox.l - line.bbox.r).max(line.bbox.l - bbox.r);
+
+            (baseline - bbox.bottom()).abs() <= opts.baseline_tolerance * h
+                && line.bbox.v_overlap(bbox) >= 0.5 * min_h
+                && gap <= opts.max_word_gap * h
+        });
+
+        match found {
+            Some((_, line)) => {
+                line.fragments.push(idx);
+                line.bbox = line.bbox.union(bbox);
+            }
+            None => lines.push((
+                bbox.bottom(),
+                Line {
+                    fragments: vec![idx],
+                    bbox: bbox.clone(),
+                },
+            )),
+        }
+    }
+
+    let boxes: Vec<_> = frags.to_vec();
+    lines
+        .into_iter()
+        .map(|(_, mut line)| {
+            line.fragments.sort_by(|a, b| {
+                let l = |i: &usize| boxes.iter().find(|f| f.0 == *i).map_or(0.0, |f| f.1.l);
+                l(a).total_cmp(&l(b))
+            });
+            line
+        })
+        .collect()
+}
+
+fn paragraphs(mut lines: Vec<Line>, h: f64, opts: &LayoutOptions) -> Vec<Paragraph> {
+    lines.sort_by(|a, b| a.bbox.top().total_cmp(&b.bbox.top()));
+
+    let mut paragraphs: Vec<Paragraph> = Vec::new();
+    for line in lines {
+        match paragraphs.last_mut() {
+            Some(p) if line.bbox.top() - p.bbox.bottom() <= opts.paragraph_gap * h => {
+                p.bbox = p.bbox.union(&line.bbox);
+                p.lines.push(line);
+            }
+            _ => paragraphs.push(Paragraph {
+                bbox: line.bbox.clone(),
+                lines: vec![line],
+            }),
+        }
+    }
+
+    paragraphs
+}
+
+fn column(lines: Vec<Line>, h: f64, opts: &LayoutOptions) -> Option<Column> {
+    let paragraphs = paragraphs(lines, h, opts);
+    let bbox = union_all(paragraphs.iter().map(|p| &p.bbox))?;
+    Some(Column { paragraphs, bbox })
+}
+
+/// horizontal extents of the columns of a page,
+/// lines wider than most of the page are ignored as they usually span columns
+fn column_ranges(lines: &[Line]) -> Vec<(f64, f64)> {
+    let left = lines.iter().map(|l| l.bbox.l).fold(f64::INFINITY, f64::min);
+    let right = lines
+        .iter()
+        .map(|l| l.bbox.r)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let page_width = right - left;
+
+    let mut ranges: Vec<(f64, f64)> = lines
+        .iter()
+        .filter(|l| l.bbox.width() <= 0.6 * page_width)
+        .map(|l| (l.bbox.l, l.bbox.r))
+        .collect();
+    ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut merged: Vec<(f64, f64)> = Vec::new();
+    for (l, r) in ranges {
+        match merged.last_mut() {
+            Some(last) if l <= last.1 => last.1 = last.1.max(r),
+            _ => merged.push((l, r)),
+        }
+    }
+
+    merged
+}
+
+fn page_layout(
+    page_no: usize,
+    frags: &[(usize, &BoundingBox)],
+    opts: &LayoutOptions,
+) -> PageLayout {
+    let h = median(frags.iter().map(|f| f.1.height()).collect());
+    let mut lines = lines(frags, h, opts);
+    lines.sort_by(|a, b| a.bbox.top().total_cmp(&b.bbox.top()));
+
+    let ranges = column_ranges(&lines);
+    if ranges.len() <= 1 {
+        return PageLayout {
+            page_no,
+            columns: column(lines, h, opts).into_iter().collect(),
+        };
+    }
+
+    let overlap =
+        |line: &Line, (l, r): (f64, f64)| (line.bbox.r.min(r) - line.bbox.l.max(l)).max(0.0);
+
+    // a band is the part of the page between two spanning lines,
+    // its columns are read left to right
+    let mut columns = Vec::new();
+    let mut band: Vec<Vec<Line>> = vec![Vec::new(); ranges.len()];
+    let flush = |band: &mut Vec<Vec<Line>>, columns: &mut Vec<Column>| {
+        for lines in band.iter_mut() {
+            columns.extend(column(std::mem::take(lines), h, opts));
+        }
+    };
+
+    for line in lines {
+        let hits = ranges.iter().filter(|r| overlap(&line, **r) > 0.0).count();
+        if hits > 1 {
+            flush(&mut band, &mut columns);
+            columns.extend(column(vec![line], h, opts));
+            continue;
+        }
+
+        let best = (0..ranges.len())
+            .max_by(|a, b| overlap(&line, ranges[*a]).total_cmp(&overlap(&line, ranges[*b])))
+            .unwrap_or(0);
+        band[best].push(line);
+    }
+    flush(&mut band, &mut columns);
+
+    PageLayout { page_no, columns }
+}
+
+impl ParsedDoc {
+    /// Groups the texts into lines, paragraphs and columns, page by page.
+    /// Texts without a bounding box are left out
+    pub fn layout(&self, opts: &LayoutOptions) -> Vec<PageLayout> {
+        let pages: BTreeSet<usize> = self
+            .texts
+            .iter()
+            .filter_map(|t| t.prov.first().map(|p| p.page_no))
+            .collect();
+
+        pages
+            .into_iter()
+            .map(|page_no| {
+                let frags: Vec<(usize, &BoundingBox)> = self
+                    .texts
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, t)| !t.text.trim().is_empty())
+                    .filter_map(|(i, t)| {
+                        let prov = t.prov.first()?;
+                        (prov.page_no == page_no).then_some((i, &prov.bbox))
+                    })
+                    .collect();
+
+                page_layout(page_no, &frags, opts)
+            })
+            .collect()
+    }
+
+    /// Text of the document in reading order with every part
+    /// mapped back to the fragment it came from
+    pub fn reading_text(&self, opts: &LayoutOptions) -> ReadingText {
+        let mut out = ReadingText::default();
+        let mut sep = "";
+
+        for page in self.layout(opts) {
+            for column in page.columns {
+                for paragraph in column.paragraphs {
+                    for line in paragraph.lines {
+                        for idx in line.fragments {
+                            let raw = &self.texts[idx].text;
+                            let text = raw.trim();
+                            out.text.push_str(sep);
+                            let start = out.text.len();
+                            out.text.push_str(text);
+                            out.spans.push(TextSpan {
+                                range: start..out.text.len(),
+                                text_idx: idx,
+                                offset: raw.len() - raw.trim_start().len(),
+                            });
+                            sep = " ";
+                        }
+                        sep = "\n";
+                    }
+                    sep = "\n\n";
+                }
+            }
+        }
+
+        out
+    }
+}
+
+#[test]
+fn reading_order() {
+    let frag = |text: &str, l: f64, t: f64, r: f64| {
+        serde_json::json!({
+            "text": text,
+            "prov": [{ "page_no": 1, "charspan": [0, text.len()], "bbox": { "l": l, "t": t, "r": r, "b": t + 10.0, "coord_origin": "TOPLEFT" } }]
+        })
+    };
+
+    let doc: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [
+            frag("right column", 300.0, 50.0, 380.0),
+            frag("Invoice Summary For Golden Waffles", 10.0, 10.0, 400.0),
+            frag("Total:", 10.0, 50.0, 50.0),
+            frag("  $28,496.68 ", 60.0, 51.0, 130.0),
+            frag("second line", 10.0, 62.0, 90.0),
+            frag("next paragraph", 10.0, 100.0, 110.0),
+            frag("continues here", 300.0, 62.0, 390.0),
+        ]
+    }))
+    .unwrap();
+
+    let layout = doc.layout(&LayoutOptions::default());
+    assert_eq!(layout[0].columns.len(), 3);
+
+    let reading = doc.reading_text(&LayoutOptions::default());
+    assert_eq!(
+        reading.text,
+        "Invoice Summary For Golden Waffles\n\nTotal: $28,496.68\nsecond line\n\nnext paragraph\n\nright column\ncontinues here"
+    );
+
+    let offset = reading.text.find("$28").unwrap();
+    assert_eq!(reading.span_at(offset).unwrap().text_idx, 3);
+    assert_eq!(
+        reading.source_ranges(offset - 3..offset + 3),
+        vec![(2, 4..6), (3, 2..5)]
+    );
+    // offsets point into the untrimmed fragment
+    let (idx, range) = reading.source_ranges(offset..offset + 10).remove(0);
+    assert_eq!(&doc.texts[idx].text[range], "$28,496.68");
+}
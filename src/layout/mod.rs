@@ -0,0 +1,5 @@
+/// Layout analysis over the boxes returned by Docling.
+/// Everything in here works locally on a `ParsedDoc`
+/// and does not make any request to the server
+pub mod kv;
+pub mod lines;
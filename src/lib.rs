@@ -1,3 +1,7 @@
+use std::sync::Mutex;
+
+use classify::{Classification, Classifier, DocKind};
+use duplicate::DuplicateStore;
 use image::DynamicImage;
 use pdf::{
     PdfEngine,
@@ -7,15 +11,42 @@ use server::{
     OcrClient,
     docling::{OcrDoc, ParsedDoc},
     invoice::InvoiceDetails,
+    options::DoclingOptions,
 };
+pub mod classify;
+pub mod date;
+pub mod duplicate;
 mod err;
+pub mod export;
+pub mod lang;
+pub mod layout;
+pub mod money;
+pub mod overlay;
 pub mod pdf;
+pub mod persist;
+pub mod pii;
+pub mod redact;
+pub mod search;
 pub mod server;
+pub mod vendor;
 pub use err::*;
 
 pub struct OcrEngine {
     pdf_engine: PdfEngine,
     client: OcrClient,
+    classifier: Classifier,
+    duplicates: Option<Mutex<DuplicateStore>>,
+}
+
+/// A document routed to an extractor based on its type
+pub enum Routed {
+    /// invoices, receipts and credit memos with their invoice details
+    Invoice {
+        doc: Box<PdfInvoiceDoc>,
+        class: Classification,
+    },
+    /// any other document, ocred if it has no text layer
+    Other { doc: PdfDoc, class: Classification },
 }
 
 impl OcrEngine {
@@ -38,12 +69,58 @@ impl OcrEngine {
         Ok(Self {
             pdf_engine: PdfEngine::new(),
             client: OcrClient::new(addr)?,
+            classifier: Classifier::default(),
+            duplicates: None,
         })
     }
 
+    /// replaces the default classification rules used by `Self::route`
+    pub fn with_classifier(mut self, classifier: Classifier) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    pub fn classify(&self, doc: &ParsedDoc) -> Classification {
+        self.classifier.classify(doc)
+    }
+
+    /// Classifies the pdf and only fetches the invoice details of
+    /// documents that have them, use instead of `Self::pdf_invoice`
+    /// on mixed mail scans
+    pub async fn route(&self, bytes: Vec<u8>) -> OcrResult<Routed> {
+        let mut doc = self.pdf_engine.doc(bytes)?;
+        let mut class = doc.classify(&self.classifier)?;
+
+        // image only PDFs have nothing to classify until they are ocred
+        if class.kind == DocKind::Unknown && !doc.imgs.is_empty() {
+            doc.ocr(&self.client).await;
+            class = doc.classify(&self.classifier)?;
+        }
+
+        if class.kind.has_invoice_details() {
+            let mut doc = Box::new(doc.into_invoice_doc(&self.client).await);
+            self.flag_duplicate(&mut doc);
+            Ok(Routed::Invoice { doc, class })
+        } else {
+            Ok(Routed::Other { doc, class })
+        }
+    }
+
     /// short hand for getting invoice and pdf in one shot
+    /// flagged if it is a duplicate, see `Self::with_duplicates`
     pub async fn pdf_invoice(&self, bytes: Vec<u8>) -> OcrResult<PdfInvoiceDoc> {
-        self.pdf_engine.invoice(&self.client, bytes).await
+        let mut doc = self.pdf_engine.invoice(&self.client, bytes).await?;
+        self.flag_duplicate(&mut doc);
+        Ok(doc)
+    }
+
+    /// same as `Self::pdf_invoice` but reads the line items from the tables of
+    /// the document when the server returns none, see `PdfDoc::into_invoice_doc_with_lines`
+    pub async fn pdf_invoice_with_lines(&self, bytes: Vec<u8>) -> OcrResult<PdfInvoiceDoc> {
+        let doc = self.pdf_engine.doc(bytes)?;
+        let mut doc = doc.into_invoice_doc_with_lines(&self.client).await;
+        self.flag_duplicate(&mut doc);
+        Ok(doc)
     }
 
     /// creates a pdf
@@ -64,4 +141,15 @@ impl OcrEngine {
         let doc = OcrDoc::from_img(img)?;
         self.client.docling(doc).await
     }
+
+    /// performs ocr on any supported document,
+    /// see `OcrDoc::from_path` and `OcrDoc::from_reader`
+    pub async fn ocr_doc(&self, doc: OcrDoc) -> OcrResult<ParsedDoc> {
+        self.client.docling(doc).await
+    }
+
+    /// same as `Self::ocr_doc` with custom conversion options
+    pub async fn ocr_doc_with(&self, doc: OcrDoc, opts: &DoclingOptions) -> OcrResult<ParsedDoc> {
+        self.client.docling_with(doc, opts).await
+    }
 }
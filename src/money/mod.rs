@@ -0,0 +1,415 @@
+/// Exact amounts of money as printed on invoices.
+/// Amounts are kept as decimals so cents do not drift through float arithmetic
+use std::{fmt, ops::Range, str::FromStr, sync::LazyLock};
+
+use regex::{Match, Regex};
+pub use rust_decimal::Decimal;
+use rust_decimal::RoundingStrategy;
+use serde::{Deserialize, Serialize};
+
+use crate::{OcrErrs, lang::Language};
+
+/// ISO 4217 currency code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const USD: Currency = Currency(*b"USD");
+    pub const EUR: Currency = Currency(*b"EUR");
+    pub const GBP: Currency = Currency(*b"GBP");
+    pub const JPY: Currency = Currency(*b"JPY");
+    pub const CAD: Currency = Currency(*b"CAD");
+    pub const AUD: Currency = Currency(*b"AUD");
+    pub const NZD: Currency = Currency(*b"NZD");
+    pub const HKD: Currency = Currency(*b"HKD");
+    pub const SGD: Currency = Currency(*b"SGD");
+    pub const MXN: Currency = Currency(*b"MXN");
+    pub const BRL: Currency = Currency(*b"BRL");
+    pub const CHF: Currency = Currency(*b"CHF");
+    pub const INR: Currency = Currency(*b"INR");
+    pub const KRW: Currency = Currency(*b"KRW");
+    pub const RUB: Currency = Currency(*b"RUB");
+
+    /// `None` unless the code is 3 ASCII letters, case is ignored
+    pub fn new(code: &str) -> Option<Self> {
+        let code: [u8; 3] = code.as_bytes().try_into().ok()?;
+        code.iter()
+            .all(u8::is_ascii_alphabetic)
+            .then(|| Currency(code.map(|c| c.to_ascii_uppercase())))
+    }
+
+    pub fn code(&self) -> &str {
+        // only ever built from ASCII letters
+        std::str::from_utf8(&self.0).unwrap()
+    }
+
+    /// digits after the decimal point of the minor unit
+    pub fn minor_units(&self) -> u32 {
+        match *self {
+            Currency::JPY | Currency::KRW => 0,
+            _ => 2,
+        }
+    }
+
+    /// currencies written with a plain `$`
+    fn is_dollar(&self) -> bool {
+        matches!(
+            *self,
+            Currency::USD
+                | Currency::CAD
+                | Currency::AUD
+                | Currency::NZD
+                | Currency::HKD
+                | Currency::SGD
+                | Currency::MXN
+        )
+    }
+
+    /// currencies whose amounts are mostly written `1.234,56`
+    fn is_comma_decimal(&self) -> bool {
+        matches!(
+            self.code(),
+            "EUR" | "BRL" | "RUB" | "SEK" | "NOK" | "DKK" | "PLN"
+        )
+    }
+}
+
+impl FromStr for Currency {
+    type Err = OcrErrs;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Currency::new(s).ok_or_else(|| OcrErrs::Currency(s.into()))
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = OcrErrs;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Currency> for String {
+    fn from(value: Currency) -> Self {
+        value.code().to_owned()
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// How the thousands and the decimal separators are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Separators {
+    /// guessed from the amount: the last separator is the decimal one
+    /// unless it is a lone comma followed by exactly 3 digits.
+    /// a lone dot followed by 3 digits is only read as thousands
+    /// by `Money::parse_with` in currencies written `1.234,56`
+    #[default]
+    Auto,
+    /// `1,234.56`
+    DotDecimal,
+    /// `1.234,56`
+    CommaDecimal,
+}
+
+impl Separators {
+    /// the separators used for numbers in the language
+    pub fn for_language(lang: Language) -> Self {
+        match lang {
+            Language::Spanish
+            | Language::French
+            | Language::German
+            | Language::Portuguese
+            | Language::Italian
+            | Language::Russian => Separators::CommaDecimal,
+            _ => Separators::DotDecimal,
+        }
+    }
+}
+
+/// How amounts are parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoneyFormat {
+    pub separators: Separators,
+    /// used when the text names no currency.
+    /// a plain `$` is read as this currency if it is a dollar
+    pub currency: Currency,
+}
+
+impl Default for MoneyFormat {
+    fn default() -> Self {
+        Self::new(Currency::USD)
+    }
+}
+
+impl MoneyFormat {
+    pub fn new(currency: Currency) -> Self {
+        Self {
+            separators: Separators::Auto,
+            currency,
+        }
+    }
+
+    pub fn separators(mut self, separators: Separators) -> Self {
+        self.separators = separators;
+        self
+    }
+}
+
+/// An exact amount in a currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    pub amount: Decimal,
+    pub currency: Currency,
+}
+
+/// numbers, spaces and apostrophes only group thousands.
+/// plain spaces only with decimal commas so `2 100.00` stays 2 numbers
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\d{1,3}(?:[\u{a0}\u{202f}']\d{3})+(?:[.,]\d+)?|\d{1,3}(?: \d{3})+,\d+|\d(?:[\d.,]*\d)?",
+    )
+    .unwrap()
+});
+
+static CODE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:^|[^\p{L}])(USD|EUR|GBP|JPY|CAD|AUD|NZD|HKD|SGD|MXN|BRL|CHF|INR|KRW|RUB|CNY|SEK|NOK|DKK|PLN|ZAR)(?:$|[^\p{L}])").unwrap()
+});
+
+static SYMBOL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[^\p{L}])((?:US|CA|AU|NZ|HK|MX|C|A|R|S)\$)|([$€£¥₹₩₽])").unwrap()
+});
+
+/// the currency named in the text and where it was found.
+/// codes win over symbols as `$` is shared by many currencies
+fn find_currency(s: &str, default: Currency) -> Option<(Currency, Range<usize>)> {
+    if let Some(m) = CODE.captures(s).and_then(|c| c.get(1)) {
+        return Some((Currency::new(m.as_str())?, m.range()));
+    }
+
+    let caps = SYMBOL.captures(s)?;
+    let m = caps.get(1).or_else(|| caps.get(2))?;
+    let currency = match m.as_str() {
+        "$" if default.is_dollar() => default,
+        "$" | "US$" => Currency::USD,
+        "CA$" | "C$" => Currency::CAD,
+        "AU$" | "A$" => Currency::AUD,
+        "NZ$" => Currency::NZD,
+        "HK$" => Currency::HKD,
+        "MX$" => Currency::MXN,
+        "R$" => Currency::BRL,
+        "S$" => Currency::SGD,
+        "€" => Currency::EUR,
+        "£" => Currency::GBP,
+        "¥" => Currency::JPY,
+        "₹" => Currency::INR,
+        "₩" => Currency::KRW,
+        _ => Currency::RUB,
+    };
+    Some((currency, m.range()))
+}
+
+/// Parses a number written with the separators, without sign or currency
+pub fn parse_number(s: &str, separators: Separators) -> Option<Decimal> {
+    parse_digits(s, separators, false)
+}
+
+/// same as `parse_number`, when guessing a lone dot followed by
+/// exactly 3 digits groups thousands if `dot_groups` is set
+fn parse_digits(s: &str, separators: Separators, dot_groups: bool) -> Option<Decimal> {
+    let digits: String = s
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\u{a0}' | '\u{202f}' | '\''))
+        .collect();
+    if digits.is_empty()
+        || !digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+    {
+        return None;
+    }
+
+    let count = |sep| digits.matches(sep).count();
+    let decimal = match separators {
+        Separators::DotDecimal => Some('.'),
+        Separators::CommaDecimal => Some(','),
+        Separators::Auto => match digits.rfind(['.', ',']) {
+            Some(idx) if count('.') > 0 && count(',') > 0 => digits[idx..].chars().next(),
+            Some(idx) if count('.') + count(',') == 1 => {
+                let sep = digits[idx..].chars().next()?;
+                let groups = digits.len() - idx - 1 == 3 && (sep == ',' || dot_groups);
+                (!groups).then_some(sep)
+            }
+            _ => None,
+        },
+    };
+
+    let normalized = match decimal {
+        Some(sep) if count(sep) > 1 => return None,
+        Some(sep) => digits
+            .replace(sep, "_")
+            .replace([',', '.'], "")
+            .replace('_', "."),
+        None => digits.replace([',', '.'], ""),
+    };
+    Decimal::from_str(&normalized).ok()
+}
+
+impl Money {
+    pub fn new(amount: Decimal, currency: Currency) -> Self {
+        Self { amount, currency }
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Self::new(Decimal::ZERO, currency)
+    }
+
+    /// parses with the default format, see `Money::parse_with`
+    pub fn parse(s: &str) -> Option<Money> {
+        Self::parse_with(s, &MoneyFormat::default())
+    }
+
+    /// Parses an amount as printed on invoices: `$1,234.50`, `1.234,56 €`,
+    /// `USD 12`, and `(12.00)`, `-12.00`, `12.00-` or `12.00 CR` for negative amounts.
+    /// The text may hold a label, the number closest to the currency is taken.
+    /// if no currency is named the last number with decimals or thousands
+    /// separators is taken, the last number if there is none
+    pub fn parse_with(s: &str, format: &MoneyFormat) -> Option<Money> {
+        let found = find_currency(s, format.currency);
+        let numbers = NUMBER.find_iter(s);
+        let number = match &found {
+            Some((_, cur)) => numbers.min_by_key(|m| distance(m, cur))?,
+            // "Total 28,496.68 (Net 30)", bare integers are rarely the amount
+            None => {
+                let numbers: Vec<_> = numbers.collect();
+                let formatted = numbers.iter().rev().find(|m| {
+                    m.as_str()
+                        .contains(['.', ',', ' ', '\u{a0}', '\u{202f}', '\''])
+                });
+                *formatted.or(numbers.last())?
+            }
+        };
+
+        let currency = found.as_ref().map_or(format.currency, |(c, _)| *c);
+        // `$2.125`/lb is a price, `2.125 €` is two thousand euros
+        let amount = parse_digits(
+            number.as_str(),
+            format.separators,
+            currency.is_comma_decimal(),
+        )?;
+
+        // the sign may be on the other side of the currency, i.e. `-$12` and `12 USD CR`
+        let mut before = &s[..number.start()];
+        let mut after = &s[number.end()..];
+        if let Some((_, cur)) = &found {
+            if cur.end <= number.start() && s[cur.end..number.start()].trim().is_empty() {
+                before = &s[..cur.start];
+            } else if cur.start >= number.end() && s[number.end()..cur.start].trim().is_empty() {
+                after = &s[cur.end..];
+            }
+        }
+
+        let before = before.trim_end();
+        let after = after.trim_start();
+        let minus = |s: &str| s.starts_with(['-', '−']);
+        let negative = (before.contains('(') && after.contains(')'))
+            || before
+                .strip_suffix(['-', '−'])
+                .is_some_and(|s| !s.ends_with(char::is_alphanumeric))
+            || minus(after)
+            || (after
+                .get(..2)
+                .is_some_and(|cr| cr.eq_ignore_ascii_case("CR"))
+                && !after[2..].starts_with(char::is_alphabetic));
+
+        Some(Money::new(
+            if negative { -amount } else { amount },
+            currency,
+        ))
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.amount.is_sign_negative() && !self.amount.is_zero()
+    }
+
+    /// rounded half away from zero to the minor unit of the currency
+    pub fn round(&self) -> Money {
+        let amount = self.amount.round_dp_with_strategy(
+            self.currency.minor_units(),
+            RoundingStrategy::MidpointAwayFromZero,
+        );
+        Money::new(amount, self.currency)
+    }
+
+    /// `None` if the currencies differ
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        (self.currency == other.currency)
+            .then(|| Money::new(self.amount + other.amount, self.currency))
+    }
+
+    /// `None` if the currencies differ
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        self.checked_add(Money::new(-other.amount, other.currency))
+    }
+
+    pub fn abs(&self) -> Money {
+        Money::new(self.amount.abs(), self.currency)
+    }
+}
+
+/// characters between the number and the currency
+fn distance(number: &Match, currency: &Range<usize>) -> usize {
+    if number.end() <= currency.start {
+        currency.start - number.end()
+    } else {
+        number.start().saturating_sub(currency.end)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)
+    }
+}
+
+#[test]
+fn parse() {
+    let money = |s: &str| Money::parse(s).map(|m| m.to_string());
+    let eur = |s: &str| Some(format!("{s} EUR"));
+    let usd = |s: &str| Some(format!("{s} USD"));
+
+    assert_eq!(money("**Total Amount:** $28,496.68"), usd("28496.68"));
+    assert_eq!(money("1.234,56 €"), eur("1234.56"));
+    assert_eq!(money("EUR 1 234,56"), eur("1234.56"));
+    assert_eq!(money("(150.00)"), usd("-150.00"));
+    assert_eq!(money("-$12.00"), usd("-12.00"));
+    assert_eq!(money("12.00-"), usd("-12.00"));
+    assert_eq!(money("12.00 USD CR"), usd("-12.00"));
+    assert_eq!(money("C$ 1,234"), Some("1234 CAD".into()));
+    assert_eq!(money("$2.125"), usd("2.125"));
+    assert_eq!(money("2.125 €"), eur("2125"));
+    assert_eq!(money("Invoice 2024-001 total 5.00 usd"), usd("5.00"));
+    assert_eq!(money("Total 28,496.68 (Net 30)"), usd("28496.68"));
+    assert_eq!(money("Qty 3 Amount 45"), usd("45"));
+    assert_eq!(money("N/A"), None);
+
+    let format = MoneyFormat::new(Currency::CAD).separators(Separators::DotDecimal);
+    let cad = Money::parse_with("$1.234", &format).unwrap();
+    assert_eq!(cad, Money::new(Decimal::new(1234, 3), Currency::CAD));
+    assert_eq!(cad.round().amount, Decimal::new(123, 2));
+    assert!(cad.checked_add(Money::zero(Currency::USD)).is_none());
+
+    let json = serde_json::to_value(Money::parse("£9.99").unwrap()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({ "amount": "9.99", "currency": "GBP" })
+    );
+    assert!(serde_json::from_value::<Currency>("US".into()).is_err());
+}
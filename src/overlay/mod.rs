@@ -0,0 +1,280 @@
+/// Debug overlays: draws what the OCR found on top of the source image
+/// so a bad extraction can be inspected visually
+pub mod font;
+
+use std::collections::BTreeSet;
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use serde::Serialize;
+
+use crate::{
+    err::OcrResult,
+    pdf::doc::PdfDoc,
+    server::docling::{BoundingBox, PageSize, ParsedDoc},
+};
+
+/// A rectangle in pixels, `x1`/`y1` are exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PixelRect {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+}
+
+impl PixelRect {
+    /// maps a box in page coordinates onto an image of the given size.
+    /// if the page size is unknown the box is assumed to be in pixels
+    pub fn from_bbox(
+        bbox: &BoundingBox,
+        page: Option<PageSize>,
+        width: u32,
+        height: u32,
+    ) -> Option<Self> {
+        let page = page.unwrap_or(PageSize {
+            width: width as f64,
+            height: height as f64,
+        });
+        if page.width <= 0.0 || page.height <= 0.0 {
+            return None;
+        }
+
+        let bbox = bbox.to_top_left(page.height);
+        let sx = width as f64 / page.width;
+        let sy = height as f64 / page.height;
+
+        let clamp = |v: f64, max: u32| v.max(0.0).min(max as f64) as u32;
+        let rect = Self {
+            x0: clamp(bbox.l.min(bbox.r) * sx, width),
+            y0: clamp(bbox.t.min(bbox.b) * sy, height),
+            x1: clamp((bbox.l.max(bbox.r) * sx).ceil(), width),
+            y1: clamp((bbox.t.max(bbox.b) * sy).ceil(), height),
+        };
+
+        (rect.x1 > rect.x0 && rect.y1 > rect.y0).then_some(rect)
+    }
+
+    /// the same rectangle grown by `px` on every side
+    pub fn pad(self, px: u32, width: u32, height: u32) -> Self {
+        Self {
+            x0: self.x0.saturating_sub(px),
+            y0: self.y0.saturating_sub(px),
+            x1: (self.x1 + px).min(width),
+            y1: (self.y1 + px).min(height),
+        }
+    }
+}
+
+/// fills the rectangle, blending the color using its alpha channel
+pub fn fill_rect(img: &mut RgbaImage, rect: PixelRect, color: Rgba<u8>) {
+    let a = color[3] as u32;
+    for y in rect.y0..rect.y1.min(img.height()) {
+        for x in rect.x0..rect.x1.min(img.width()) {
+            let px = img.get_pixel_mut(x, y);
+            for c in 0..3 {
+                px[c] = ((color[c] as u32 * a + px[c] as u32 * (255 - a)) / 255) as u8;
+            }
+            px[3] = px[3].max(color[3]);
+        }
+    }
+}
+
+/// draws the outline of the rectangle
+pub fn stroke_rect(img: &mut RgbaImage, rect: PixelRect, thickness: u32, color: Rgba<u8>) {
+    let t = thickness.max(1);
+    let edges = [
+        PixelRect {
+            y1: (rect.y0 + t).min(rect.y1),
+            ..rect
+        },
+        PixelRect {
+            y0: rect.y1.saturating_sub(t).max(rect.y0),
+            ..rect
+        },
+        PixelRect {
+            x1: (rect.x0 + t).min(rect.x1),
+            ..rect
+        },
+        PixelRect {
+            x0: rect.x1.saturating_sub(t).max(rect.x0),
+            ..rect
+        },
+    ];
+
+    for edge in edges {
+        fill_rect(img, edge, color);
+    }
+}
+
+/// color used for an element type
+pub fn label_color(label: Option<&str>) -> Rgba<u8> {
+    match label.unwrap_or("text") {
+        "text" | "paragraph" => Rgba([31, 119, 180, 255]),
+        "title" | "section_header" => Rgba([214, 39, 40, 255]),
+        "page_header" | "page_footer" => Rgba([127, 127, 127, 255]),
+        "list_item" => Rgba([44, 160, 44, 255]),
+        "caption" | "footnote" => Rgba([148, 103, 189, 255]),
+        "table" | "document_index" => Rgba([255, 127, 14, 255]),
+        "key_value_region" | "form" => Rgba([23, 190, 207, 255]),
+        "checkbox_selected" | "checkbox_unselected" => Rgba([188, 189, 34, 255]),
+        _ => Rgba([140, 86, 75, 255]),
+    }
+}
+
+/// Controls what is drawn on the overlay
+#[derive(Debug, Clone)]
+pub struct OverlayOptions {
+    /// thickness of the box outlines in pixels
+    pub thickness: u32,
+    /// draw the index and the element type of every box
+    pub labels: bool,
+    /// size of a font pixel, 0 picks one based on the image size
+    pub font_scale: u32,
+    /// indexes into `ParsedDoc::texts` to highlight, i.e. search hits
+    pub highlights: BTreeSet<usize>,
+    pub highlight_color: Rgba<u8>,
+}
+
+impl Default for OverlayOptions {
+    fn default() -> Self {
+        Self {
+            thickness: 2,
+            labels: true,
+            font_scale: 0,
+            highlights: BTreeSet::new(),
+            highlight_color: Rgba([255, 230, 0, 110]),
+        }
+    }
+}
+
+impl OverlayOptions {
+    /// highlights the given texts, accepts the `text_idx` of search hits
+    pub fn highlight<I: IntoIterator<Item = usize>>(mut self, text_idxs: I) -> Self {
+        self.highlights.extend(text_idxs);
+        self
+    }
+}
+
+/// Draws every box of the given page of the document on top of the image.
+/// the image is expected to be a render of that page
+pub fn draw_overlay(
+    img: &DynamicImage,
+    doc: &ParsedDoc,
+    page_no: usize,
+    opts: &OverlayOptions,
+) -> DynamicImage {
+    let mut out = img.to_rgba8();
+    let (width, height) = out.dimensions();
+    let page = doc.pages.get(&page_no).map(|p| p.size);
+    let scale = match opts.font_scale {
+        0 => (width / 600).max(2),
+        s => s,
+    };
+
+    let rects: Vec<_> = doc
+        .texts
+        .iter()
+        .enumerate()
+        .flat_map(|(i, t)| t.prov.iter().map(move |p| (i, t, p)))
+        .filter(|(_, _, p)| p.page_no == page_no)
+        .filter_map(|(i, t, p)| Some((i, t, PixelRect::from_bbox(&p.bbox, page, width, height)?)))
+        .collect();
+
+    for (i, _, rect) in &rects {
+        if opts.highlights.contains(i) {
+            fill_rect(&mut out, *rect, opts.highlight_color);
+        }
+    }
+
+    for (_, text, rect) in &rects {
+        stroke_rect(
+            &mut out,
+            *rect,
+            opts.thickness,
+            label_color(text.label.as_deref()),
+        );
+    }
+
+    if opts.labels {
+        for (i, text, rect) in &rects {
+            let color = label_color(text.label.as_deref());
+            let tag = format!(
+                "{i} {}",
+                text.label.as_deref().unwrap_or("text").replace('_', "-")
+            );
+            let (tw, th) = font::text_size(&tag, scale);
+
+            // above the box if there is room, inside otherwise
+            let y = rect.y0.checked_sub(th + 2 * scale).unwrap_or(rect.y0);
+            let bg = PixelRect {
+                x0: rect.x0,
+                y0: y,
+                x1: (rect.x0 + tw + 2 * scale).min(width),
+                y1: (y + th + 2 * scale).min(height),
+            };
+            fill_rect(&mut out, bg, color);
+            font::draw_text(
+                &mut out,
+                rect.x0 + scale,
+                y + scale,
+                &tag,
+                scale,
+                Rgba([255, 255, 255, 255]),
+            );
+        }
+    }
+
+    DynamicImage::ImageRgba8(out)
+}
+
+impl PdfDoc {
+    /// renders the page (0 based index) and draws the boxes
+    /// of `doc` that belong to it (page number `page_idx + 1`)
+    pub fn overlay_page(
+        &self,
+        page_idx: u16,
+        doc: &ParsedDoc,
+        opts: &OverlayOptions,
+    ) -> OcrResult<DynamicImage> {
+        let img = self.render_page(page_idx)?;
+        Ok(draw_overlay(&img, doc, page_idx as usize + 1, opts))
+    }
+
+    /// draws the ocr result of an embedded image on top of it,
+    /// `None` if the image has not been ocred or failed
+    pub fn overlay_img(&self, img_idx: usize, opts: &OverlayOptions) -> Option<DynamicImage> {
+        let img = self.imgs.get(img_idx)?;
+        let doc = self.parsed_doc.get(img_idx)?.as_ref().ok()?;
+        Some(draw_overlay(img, doc, 1, opts))
+    }
+}
+
+#[test]
+fn overlay() {
+    let doc: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [
+            { "text": "INVOICE", "label": "section_header", "prov": [{ "page_no": 1, "charspan": [0, 7], "bbox": { "l": 10.0, "t": 90.0, "r": 50.0, "b": 80.0, "coord_origin": "BOTTOMLEFT" } }] },
+            { "text": "Total", "prov": [{ "page_no": 1, "charspan": [0, 5], "bbox": { "l": 10.0, "t": 40.0, "r": 50.0, "b": 30.0, "coord_origin": "BOTTOMLEFT" } }] },
+        ],
+        "pages": { "1": { "page_no": 1, "size": { "width": 100.0, "height": 100.0 } } }
+    }))
+    .unwrap();
+
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(200, 200, Rgba([255, 255, 255, 255])));
+    let opts = OverlayOptions {
+        labels: false,
+        ..Default::default()
+    }
+    .highlight([1]);
+    let out = draw_overlay(&img, &doc, 1, &opts).to_rgba8();
+
+    // the page is scaled 2x and flipped, "INVOICE" sits at y 20..40
+    assert_eq!(*out.get_pixel(20, 20), label_color(Some("section_header")));
+    assert_eq!(*out.get_pixel(60, 30), Rgba([255, 255, 255, 255]));
+    // "Total" is highlighted
+    assert_ne!(*out.get_pixel(60, 130), Rgba([255, 255, 255, 255]));
+    assert_eq!(*out.get_pixel(150, 150), Rgba([255, 255, 255, 255]));
+
+    let labelled = draw_overlay(&img, &doc, 1, &OverlayOptions::default());
+    assert_ne!(labelled.to_rgba8(), out);
+}
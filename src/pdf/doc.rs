@@ -1,15 +1,30 @@
 use crate::{
+    duplicate::DuplicateMatch,
     err::OcrResult,
+    lang::Language,
+    search::fuzzy::{FuzzyHit, FuzzyOptions, HitSource, fuzzy_find},
     server::{
         OcrClient,
         docling::{OcrDoc, ParsedDoc},
         invoice::InvoiceDetails,
+        options::DoclingOptions,
     },
 };
 use image::DynamicImage;
 use pdf::prelude::*;
 
+/// config used to render pages, 2000px wide at most 2000px high
+pub(crate) fn render_config() -> PdfRenderConfig {
+    PdfRenderConfig::new()
+        .set_target_width(2000)
+        .set_maximum_height(2000)
+}
+
 pub struct PdfDoc {
+    /// name of the original file, used to name the pages sent to the server
+    pub name: Option<String>,
+    /// languages of the document, forwarded to the server with every request
+    pub lang: Vec<Language>,
     pub bytes: Vec<u8>,
     // pub(crate) doc: PdfDocument<'a>,
     pub(crate) pdfium: Pdfium,
@@ -19,10 +34,37 @@ pub struct PdfDoc {
 }
 
 impl PdfDoc {
-    pub fn load(&self) -> OcrResult<PdfDocument> {
+    pub fn load(&self) -> OcrResult<PdfDocument<'_>> {
         Ok(self.pdfium.load_pdf_from_byte_slice(&self.bytes, None)?)
     }
 
+    /// sets the name of the original file
+    pub fn with_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// sets the languages of the document
+    pub fn with_lang<I: IntoIterator<Item = Language>>(mut self, langs: I) -> Self {
+        self.lang = langs.into_iter().collect();
+        self
+    }
+
+    /// name of the original file or `[Unknown].pdf`
+    pub fn file_name(&self) -> &str {
+        self.name.as_deref().unwrap_or("[Unknown].pdf")
+    }
+
+    /// renders the page (0 based index) into an image
+    /// 2000px wide at most 2000px high.
+    /// Beware that this does not perform any rotation on the page
+    pub fn render_page(&self, page_idx: u16) -> OcrResult<DynamicImage> {
+        let doc = self.load()?;
+        let page = doc.pages().get(page_idx)?;
+
+        Ok(page.render_with_config(&render_config())?.as_image())
+    }
+
     // will search thru the document
     pub fn contains(&self, needle: &str) -> bool {
         // search thru to the ocred items first
@@ -52,9 +94,64 @@ impl PdfDoc {
         false
     }
 
-    async fn ocr_img(client: &OcrClient, img: &DynamicImage) -> OcrResult<ParsedDoc> {
-        let doc = OcrDoc::from_img(img)?;
-        client.docling(doc).await
+    /// fuzzy search thru the ocred items and the text layer of every page
+    /// returns where the hit was found alongside the hit
+    pub fn fuzzy_search(&self, needle: &str, opts: &FuzzyOptions) -> Vec<(HitSource, FuzzyHit)> {
+        let mut hits: Vec<_> = self
+            .parsed_doc
+            .iter()
+            .enumerate()
+            .filter_map(|(i, parsed)| parsed.as_ref().ok().map(|p| (i, p)))
+            .flat_map(|(i, parsed)| {
+                parsed
+                    .fuzzy_search(needle, opts)
+                    .into_iter()
+                    .map(move |hit| (HitSource::Image(i), hit))
+            })
+            .collect();
+
+        if let Ok(doc) = self.load() {
+            for (idx, page) in doc.pages().iter().enumerate() {
+                let Ok(text) = page.text() else { continue };
+                let source = HitSource::TextLayer(idx as u16);
+                for (line_idx, line) in text.all().lines().enumerate() {
+                    hits.extend(fuzzy_find(line, needle, opts).into_iter().map(
+                        |(range, distance, score)| {
+                            let hit = FuzzyHit {
+                                text_idx: line_idx,
+                                matched: line[range.clone()].to_owned(),
+                                range,
+                                distance,
+                                score,
+                            };
+                            (source, hit)
+                        },
+                    ));
+                }
+            }
+        }
+
+        hits.sort_by(|a, b| b.1.score.total_cmp(&a.1.score));
+        hits
+    }
+
+    /// all the ocred images merged into one document,
+    /// each image is treated as a page of its own
+    pub fn merged_doc(&self) -> ParsedDoc {
+        ParsedDoc::merge(&self.parsed_doc)
+    }
+
+    async fn ocr_img(
+        &self,
+        client: &OcrClient,
+        img_idx: usize,
+        opts: Option<&DoclingOptions>,
+    ) -> OcrResult<ParsedDoc> {
+        let doc = OcrDoc::from_pdf_img(self, img_idx)?;
+        match opts {
+            Some(opts) => client.docling_with(doc, opts).await,
+            None => client.docling(doc).await,
+        }
     }
 
     /// Extract relevenat data from images if possible
@@ -65,8 +162,15 @@ impl PdfDoc {
     ///  - Only performs OCR on the images that are incompleted/ or have not been done
     pub async fn ocr(&mut self, client: &OcrClient) {
         for i in self.parsed_doc.len()..self.imgs.len() {
-            let img = &self.imgs[i];
-            let parsed = Self::ocr_img(client, img).await;
+            let parsed = self.ocr_img(client, i, None).await;
+            self.parsed_doc.push(parsed);
+        }
+    }
+
+    /// same as `Self::ocr` with custom conversion options
+    pub async fn ocr_with(&mut self, client: &OcrClient, opts: &DoclingOptions) {
+        for i in self.parsed_doc.len()..self.imgs.len() {
+            let parsed = self.ocr_img(client, i, Some(opts)).await;
             self.parsed_doc.push(parsed);
         }
     }
@@ -77,41 +181,70 @@ impl PdfDoc {
     /// if there multiple pages to the invoice
     /// it will fetch info on the first page only
     /// as most of the invoice contain needed info on the first page
-    /// in order to convert page image it needs to render into bitmap
-    /// and to convert it to pixels for image it needs height and width
     pub(crate) async fn invoice_info(&self, client: &OcrClient) -> OcrResult<InvoiceDetails> {
         self.invoice_info_wh(client).await
     }
 
-    /// gets invoice data will from this pdf file
-    /// only the first page is sent for processing
-    /// takes height and width at which the image will be generated
-    /// consider using `Self::invoice_info` which renders the at @200dpi
-    /// Beware that this does not perform any rotation on an the page
+    /// gets invoice data from this pdf file
+    /// only the first page is sent for processing, rendered by
+    /// `OcrDoc::from_pdf_page` 2000px wide at most 2000px high
+    /// and named after the file with the languages of the document.
+    /// Beware that this does not perform any rotation on the page
     pub(crate) async fn invoice_info_wh(&self, client: &OcrClient) -> OcrResult<InvoiceDetails> {
-        let doc = self.load()?;
-        let first_page = doc.pages().first()?;
-        println!(
-            "{}, {}",
-            first_page.height().to_mm(),
-            first_page.width().to_mm()
-        );
+        let doc = OcrDoc::from_pdf_page(self, 0)?;
+        client.invoice_doc(doc).await
+    }
 
-        let render_config = PdfRenderConfig::new()
-            .set_target_width(2000)
-            .set_maximum_height(2000);
+    /// the whole pdf converted by Docling, which reads the tables
+    /// of the text layer as well as the ones of the scanned pages
+    pub async fn docling(&self, client: &OcrClient) -> OcrResult<ParsedDoc> {
+        client.docling(OcrDoc::from_pdf(self)).await
+    }
 
-        let first_page_img = first_page.render_with_config(&render_config)?.as_image();
+    /// Gets the invoice details, the line items are read from the tables
+    /// of the images already ocred if the server did not return any.
+    /// makes a single request, see `Self::into_invoice_doc_with_lines`
+    pub async fn into_invoice_doc(self, client: &OcrClient) -> PdfInvoiceDoc {
+        let mut invoice_details = self.invoice_info(client).await;
+        if let Ok(details) = &mut invoice_details {
+            details.fill_line_items(&self.merged_doc());
+        }
 
-        client.invoice(&first_page_img).await
+        PdfInvoiceDoc {
+            doc: self,
+            invoice_details,
+            docling: None,
+            duplicate: Ok(None),
+        }
     }
 
-    pub async fn into_invoice_doc(self, client: &OcrClient) -> PdfInvoiceDoc {
-        let invoice_details = self.invoice_info(client).await;
+    /// same as `Self::into_invoice_doc` but if the server did not return
+    /// any line item the images are ocred first if they were not,
+    /// and the whole pdf goes thru Docling if they hold no table.
+    /// costs a request per image plus one, the Docling result is kept
+    /// in `PdfInvoiceDoc::docling`
+    pub async fn into_invoice_doc_with_lines(mut self, client: &OcrClient) -> PdfInvoiceDoc {
+        let mut invoice_details = self.invoice_info(client).await;
+        let mut docling = None;
+        if let Ok(details) = &mut invoice_details
+            && details.line_items.is_empty()
+        {
+            self.ocr(client).await;
+            details.fill_line_items(&self.merged_doc());
+            if details.line_items.is_empty() {
+                let converted = self.docling(client).await;
+                if let Ok(converted) = &converted {
+                    details.fill_line_items(converted);
+                }
+                docling = Some(converted);
+            }
+        }
 
         PdfInvoiceDoc {
             doc: self,
             invoice_details,
+            docling,
+            duplicate: Ok(None),
         }
     }
 }
@@ -120,4 +253,9 @@ impl PdfDoc {
 pub struct PdfInvoiceDoc {
     pub doc: PdfDoc,
     pub invoice_details: OcrResult<InvoiceDetails>,
+    /// the whole pdf converted by Docling to read the line items,
+    /// see `PdfDoc::into_invoice_doc_with_lines`
+    pub docling: Option<OcrResult<ParsedDoc>>,
+    /// the earlier copy of the invoice, see `PdfInvoiceDoc::check_duplicate`
+    pub duplicate: OcrResult<Option<DuplicateMatch>>,
 }
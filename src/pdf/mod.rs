@@ -1,6 +1,7 @@
 use doc::{PdfDoc, PdfInvoiceDoc};
 use pdf::prelude::*;
 pub mod doc;
+pub mod searchable;
 use crate::{err::OcrResult, server::OcrClient};
 
 // use std::{
@@ -58,7 +59,7 @@ fn load_lib() -> OcrResult<Pdfium> {
     Ok(pdfium)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct PdfEngine {
     // pdfium: Pdfium,
     _a: (),
@@ -79,16 +80,18 @@ impl PdfEngine {
 
             for page in pages {
                 for obj in page.objects().iter() {
-                    if let Some(image) = obj.as_image_object() {
-                        if let Ok(image) = image.get_raw_image() {
-                            imgs.push(image);
-                        }
+                    if let Some(image) = obj.as_image_object()
+                        && let Ok(image) = image.get_raw_image()
+                    {
+                        imgs.push(image);
                     }
                 }
             }
         }
 
         let pdf = PdfDoc {
+            name: None,
+            lang: Vec::new(),
             pdfium,
             bytes,
             imgs,
@@ -97,6 +100,11 @@ impl PdfEngine {
         Ok(pdf)
     }
 
+    /// same as `Self::doc` but keeps the name of the original file
+    pub fn named_doc<S: Into<String>>(&self, name: S, bytes: Vec<u8>) -> OcrResult<PdfDoc> {
+        Ok(self.doc(bytes)?.with_name(name))
+    }
+
     pub async fn invoice(&self, client: &OcrClient, bytes: Vec<u8>) -> OcrResult<PdfInvoiceDoc> {
         Ok(self.doc(bytes)?.into_invoice_doc(client).await)
     }
@@ -0,0 +1,201 @@
+use pdf::prelude::*;
+
+use crate::{
+    err::OcrResult,
+    pdf::doc::PdfDoc,
+    server::docling::{BoundingBox, PageSize, ParsedDoc},
+};
+
+/// A rectangle in PDF points, the origin is at the bottom left of the page
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointRect {
+    pub left: f32,
+    pub bottom: f32,
+    pub right: f32,
+    pub top: f32,
+}
+
+impl PointRect {
+    pub fn width(&self) -> f32 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> f32 {
+        self.top - self.bottom
+    }
+
+    /// maps a box of an ocred image of the given size onto
+    /// `area`, the rectangle the image covers on the page
+    pub fn from_bbox(bbox: &BoundingBox, img: PageSize, area: PointRect) -> Option<Self> {
+        if img.width <= 0.0 || img.height <= 0.0 {
+            return None;
+        }
+
+        let bbox = bbox.to_top_left(img.height);
+        let sx = area.width() as f64 / img.width;
+        let sy = area.height() as f64 / img.height;
+        let rect = Self {
+            left: area.left + (bbox.l.min(bbox.r) * sx) as f32,
+            right: area.left + (bbox.l.max(bbox.r) * sx) as f32,
+            top: area.top - (bbox.t.min(bbox.b) * sy) as f32,
+            bottom: area.top - (bbox.t.max(bbox.b) * sy) as f32,
+        };
+
+        (rect.width() > 0.0 && rect.height() > 0.0).then_some(rect)
+    }
+}
+
+impl From<PdfRect> for PointRect {
+    fn from(r: PdfRect) -> Self {
+        Self {
+            left: r.left().value,
+            bottom: r.bottom().value,
+            right: r.right().value,
+            top: r.top().value,
+        }
+    }
+}
+
+/// share of the box height used as font size, the rest is left for descenders
+const FONT_SCALE: f32 = 0.8;
+
+/// adds the text as invisible text stretched over the rectangle
+fn add_text<'a>(
+    doc: &PdfDocument<'a>,
+    page: &mut PdfPage<'a>,
+    font: PdfFontToken,
+    text: &str,
+    rect: PointRect,
+) -> OcrResult<()> {
+    let font_size = rect.height() * FONT_SCALE;
+    let mut obj = PdfPageTextObject::new(doc, text, font, PdfPoints::new(font_size))?;
+    obj.set_render_mode(PdfPageTextRenderMode::Invisible)?;
+
+    let width = obj.width()?.value;
+    if width > 0.0 {
+        obj.scale(rect.width() / width, 1.0)?;
+    }
+    obj.translate(
+        PdfPoints::new(rect.left),
+        PdfPoints::new(rect.bottom + rect.height() * (1.0 - FONT_SCALE) / 2.0),
+    )?;
+
+    page.objects_mut().add_text_object(obj)?;
+    Ok(())
+}
+
+impl PdfDoc {
+    /// Builds a searchable copy of the document: the ocred text of every
+    /// embedded image is written as invisible text on top of the image.
+    /// `Self::ocr` must have been called first, images that were not
+    /// ocred are left as is. the standard Helvetica font is used,
+    /// which only covers latin text, see `Self::searchable_with_font`
+    pub fn searchable(&self) -> OcrResult<Vec<u8>> {
+        self.searchable_pdf(None)
+    }
+
+    /// same as `Self::searchable` using the given TrueType font,
+    /// which should cover every language of the document
+    pub fn searchable_with_font(&self, ttf: &[u8]) -> OcrResult<Vec<u8>> {
+        self.searchable_pdf(Some(ttf))
+    }
+
+    fn searchable_pdf(&self, ttf: Option<&[u8]>) -> OcrResult<Vec<u8>> {
+        let mut doc = self.load()?;
+        let font = match ttf {
+            Some(ttf) => doc.fonts_mut().load_true_type_from_bytes(ttf, true)?,
+            None => doc.fonts_mut().helvetica(),
+        };
+
+        let placements = image_placements(&doc)?;
+        for (img_idx, (page_idx, area)) in placements.into_iter().enumerate() {
+            let Some(Ok(parsed)) = self.parsed_doc.get(img_idx) else {
+                continue;
+            };
+            let img = self.imgs.get(img_idx).map(|i| PageSize {
+                width: i.width() as f64,
+                height: i.height() as f64,
+            });
+
+            let mut page = doc.pages().get(page_idx)?;
+            for (text, rect) in text_rects(parsed, img, area) {
+                add_text(&doc, &mut page, font, text, rect)?;
+            }
+        }
+
+        Ok(doc.save_to_bytes()?)
+    }
+}
+
+/// page index and area of every embedded image,
+/// images are numbered the same way as in `PdfEngine::doc`
+pub(crate) fn image_placements(doc: &PdfDocument) -> OcrResult<Vec<(u16, PointRect)>> {
+    let mut placements = Vec::new();
+    for (page_idx, page) in doc.pages().iter().enumerate() {
+        for obj in page.objects().iter() {
+            if let Some(image) = obj.as_image_object()
+                && image.get_raw_image().is_ok()
+            {
+                placements.push((page_idx as u16, obj.bounds()?.to_rect().into()));
+            }
+        }
+    }
+    Ok(placements)
+}
+
+/// the texts of an ocred image with their place on the page.
+/// boxes are in the page size reported by the server,
+/// or in pixels of the image if it is missing
+fn text_rects(
+    parsed: &ParsedDoc,
+    img: Option<PageSize>,
+    area: PointRect,
+) -> impl Iterator<Item = (&str, PointRect)> {
+    parsed.texts.iter().flat_map(move |t| {
+        t.prov
+            .iter()
+            // an empty text object crashes pdfium
+            .filter(|_| !t.text.trim().is_empty())
+            .filter_map(move |p| {
+                let size = parsed.pages.get(&p.page_no).map(|p| p.size).or(img)?;
+                Some((t.text.as_str(), PointRect::from_bbox(&p.bbox, size, area)?))
+            })
+    })
+}
+
+#[test]
+fn placement() {
+    let doc: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [
+            { "text": "INVOICE", "prov": [{ "page_no": 1, "charspan": [0, 7], "bbox": { "l": 100.0, "t": 100.0, "r": 500.0, "b": 200.0, "coord_origin": "TOPLEFT" } }] },
+            { "text": " ", "prov": [{ "page_no": 1, "charspan": [0, 1], "bbox": { "l": 0.0, "t": 0.0, "r": 10.0, "b": 10.0, "coord_origin": "TOPLEFT" } }] },
+        ],
+    }))
+    .unwrap();
+
+    // a 1000x2000 px scan covering a page from (0, 0) to (500, 1000)
+    let img = PageSize {
+        width: 1000.0,
+        height: 2000.0,
+    };
+    let area = PointRect {
+        left: 0.0,
+        bottom: 0.0,
+        right: 500.0,
+        top: 1000.0,
+    };
+    let rects: Vec<_> = text_rects(&doc, Some(img), area).collect();
+
+    assert_eq!(
+        rects,
+        vec![(
+            "INVOICE",
+            PointRect {
+                left: 50.0,
+                bottom: 900.0,
+                right: 250.0,
+                top: 950.0
+            }
+        )]
+    );
+}
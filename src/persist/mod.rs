@@ -0,0 +1,216 @@
+/// Storing OCR results so they can be reloaded later
+/// without making another request to the server.
+/// Everything is stored as JSON inside an envelope that records
+/// the kind of the result and the schema version it was written with
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::{
+    OcrErrs,
+    err::OcrResult,
+    server::{
+        docling::ParsedDoc,
+        invoice::{InvoiceDetails, InvoiceResponse},
+    },
+};
+
+/// A result type that can be persisted
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// name of the type stored in the envelope
+    const KIND: &'static str;
+    /// current schema version
+    const VERSION: u32;
+
+    /// loads data that was stored without an envelope,
+    /// i.e. the raw response of the server
+    fn from_unversioned(data: Value) -> OcrResult<Self>;
+
+    /// migrates data stored at `version` to `version + 1`
+    fn upgrade(version: u32, data: Value) -> OcrResult<Value> {
+        let _ = data;
+        Err(OcrErrs::UnsupportedVersion {
+            kind: Self::KIND.into(),
+            version,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope<T> {
+    kind: String,
+    version: u32,
+    data: T,
+}
+
+impl Versioned for ParsedDoc {
+    const KIND: &'static str = "parsed_doc";
+    const VERSION: u32 = 1;
+
+    fn from_unversioned(data: Value) -> OcrResult<Self> {
+        Ok(serde_json::from_value(data)?)
+    }
+}
+
+impl Versioned for InvoiceDetails {
+    const KIND: &'static str = "invoice_details";
+    const VERSION: u32 = 2;
+
+    fn from_unversioned(data: Value) -> OcrResult<Self> {
+        let res: InvoiceResponse = serde_json::from_value(data)?;
+        Ok(res.into())
+    }
+
+    fn upgrade(version: u32, mut data: Value) -> OcrResult<Value> {
+        match version {
+            // amounts were f64 in dollars
+            1 => {
+                let money = |v: Option<&mut Value>| {
+                    if let Some(v) = v.filter(|v| v.is_number()) {
+                        *v = serde_json::json!({ "amount": v.to_string(), "currency": "USD" });
+                    }
+                };
+                money(data.pointer_mut("/total/Left"));
+                money(data.get_mut("subtotal"));
+                if let Some(Value::Array(items)) = data.get_mut("line_items") {
+                    for item in items {
+                        money(item.get_mut("unit_price"));
+                        money(item.get_mut("amount"));
+                    }
+                }
+                Ok(data)
+            }
+            _ => Err(OcrErrs::UnsupportedVersion {
+                kind: Self::KIND.into(),
+                version,
+            }),
+        }
+    }
+}
+
+/// wraps the value in a versioned envelope
+pub fn to_value<T: Versioned>(value: &T) -> OcrResult<Value> {
+    Ok(serde_json::to_value(Envelope {
+        kind: T::KIND.into(),
+        version: T::VERSION,
+        data: value,
+    })?)
+}
+
+/// loads a value stored by `to_value`, older versions are
+/// migrated forward. Values without an envelope are treated
+/// as the raw response of the server
+pub fn from_value<T: Versioned>(value: Value) -> OcrResult<T> {
+    let is_envelope = value.get("kind").is_some_and(Value::is_string)
+        && value.get("version").is_some_and(Value::is_u64)
+        && value.get("data").is_some();
+
+    if !is_envelope {
+        return T::from_unversioned(value);
+    }
+
+    let Envelope {
+        kind,
+        version,
+        mut data,
+    } = serde_json::from_value::<Envelope<Value>>(value)?;
+
+    if kind != T::KIND {
+        return Err(OcrErrs::KindMismatch {
+            expected: T::KIND.into(),
+            found: kind,
+        });
+    }
+
+    if version == 0 || version > T::VERSION {
+        return Err(OcrErrs::UnsupportedVersion { kind, version });
+    }
+
+    for v in version..T::VERSION {
+        data = T::upgrade(v, data)?;
+    }
+
+    Ok(serde_json::from_value(data)?)
+}
+
+pub fn to_string<T: Versioned>(value: &T) -> OcrResult<String> {
+    Ok(serde_json::to_string(&to_value(value)?)?)
+}
+
+pub fn from_str<T: Versioned>(s: &str) -> OcrResult<T> {
+    from_value(serde_json::from_str(s)?)
+}
+
+pub fn to_writer<T: Versioned, W: Write>(writer: W, value: &T) -> OcrResult<()> {
+    Ok(serde_json::to_writer(writer, &to_value(value)?)?)
+}
+
+pub fn from_reader<T: Versioned, R: Read>(reader: R) -> OcrResult<T> {
+    from_value(serde_json::from_reader(reader)?)
+}
+
+/// writes the value to a file, overwriting it if it exists
+pub fn save<T: Versioned, P: AsRef<Path>>(path: P, value: &T) -> OcrResult<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    to_writer(&mut writer, value)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// reads a value written by `save`
+pub fn load<T: Versioned, P: AsRef<Path>>(path: P) -> OcrResult<T> {
+    from_reader(BufReader::new(File::open(path)?))
+}
+
+#[test]
+fn round_trip() {
+    let raw = serde_json::json!({
+        "invoice_no": "INV-1001",
+        "vendor": "Golden Waffles",
+        "acct_no": null,
+        "inv_date": "01/05/2024",
+        "due_date": "Net 30",
+        "total": "$28,496.68"
+    });
+
+    let details: InvoiceDetails = from_value(raw).unwrap();
+    let stored = to_string(&details).unwrap();
+    let loaded: InvoiceDetails = from_str(&stored).unwrap();
+    assert_eq!(loaded.inv_no.as_deref(), Some("INV-1001"));
+    assert_eq!(loaded.due_date, details.due_date);
+    assert_eq!(loaded.total, details.total);
+
+    let v1 = serde_json::json!({
+        "kind": "invoice_details",
+        "version": 1,
+        "data": {
+            "inv_no": "INV-1001", "vendor": null, "acct_no": null,
+            "inv_date": { "Right": "No Date Available" }, "due_date": { "Right": "No Date Available" },
+            "total": { "Left": 28496.68 },
+            "line_items": [{ "description": "Waffles", "quantity": 2.0, "unit_price": 0.1, "amount": null }]
+        }
+    });
+    let upgraded: InvoiceDetails = from_value(v1).unwrap();
+    let money = crate::money::Money::parse;
+    assert_eq!(upgraded.total.left(), money("$28,496.68"));
+    assert_eq!(upgraded.line_items[0].unit_price, money("0.10"));
+
+    let doc: ParsedDoc = from_value(serde_json::json!({ "texts": [] })).unwrap();
+    let stored = to_value(&doc).unwrap();
+    assert!(matches!(
+        from_value::<InvoiceDetails>(stored.clone()),
+        Err(OcrErrs::KindMismatch { .. })
+    ));
+
+    let mut future = stored;
+    future["version"] = (ParsedDoc::VERSION + 1).into();
+    assert!(matches!(
+        from_value::<ParsedDoc>(future),
+        Err(OcrErrs::UnsupportedVersion { .. })
+    ));
+}
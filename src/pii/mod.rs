@@ -0,0 +1,464 @@
+/// Detection of personal and payment data in the text of a document,
+/// meant to be run before the text is forwarded to other systems
+use std::{ops::Range, sync::LazyLock};
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    err::OcrResult,
+    pdf::{doc::PdfDoc, searchable::image_placements},
+    redact::{RedactSource, Redaction, luhn, mask},
+    server::docling::ParsedDoc,
+};
+
+/// Kind of personal data found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    /// payment card number, Luhn validated
+    CardNumber,
+    /// US social security number
+    Ssn,
+    /// bank account number or IBAN
+    BankAccount,
+    /// ABA routing number, checksum validated
+    RoutingNumber,
+    DateOfBirth,
+    Email,
+    Phone,
+}
+
+impl PiiKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PiiKind::CardNumber => "card_number",
+            PiiKind::Ssn => "ssn",
+            PiiKind::BankAccount => "bank_account",
+            PiiKind::RoutingNumber => "routing_number",
+            PiiKind::DateOfBirth => "date_of_birth",
+            PiiKind::Email => "email",
+            PiiKind::Phone => "phone",
+        }
+    }
+
+    /// how bad it is if the value leaks
+    pub fn risk(&self) -> Risk {
+        match self {
+            PiiKind::CardNumber | PiiKind::Ssn => Risk::Critical,
+            // a routing number leaks as much as the account it goes with
+            PiiKind::BankAccount | PiiKind::RoutingNumber => Risk::High,
+            PiiKind::DateOfBirth => Risk::Medium,
+            PiiKind::Email | PiiKind::Phone => Risk::Low,
+        }
+    }
+}
+
+/// Risk classification of a finding, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Risk {
+    /// publicly available or business contact data
+    Low,
+    /// identifies a person when combined with other data
+    Medium,
+    /// enables fraud on its own
+    High,
+    /// regulated data (PCI, SSN) that must not be stored in clear
+    Critical,
+}
+
+/// Where a finding comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "source")]
+pub enum PiiSource {
+    /// an `OcrText` of a `ParsedDoc`, by index into `ParsedDoc::texts`
+    Ocr { text_idx: usize },
+    /// an `OcrText` of an embedded image of a pdf,
+    /// by index into `PdfDoc::imgs` and into the `ParsedDoc::texts` of the image
+    PdfImage { img_idx: usize, text_idx: usize },
+    /// the text layer of a PDF page (0 based index)
+    TextLayer { page_idx: u16 },
+}
+
+/// A single piece of personal data found in a document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PiiFinding {
+    pub kind: PiiKind,
+    pub risk: Risk,
+    #[serde(flatten)]
+    pub source: PiiSource,
+    /// page number of the text, 1 based
+    pub page_no: Option<usize>,
+    /// byte range of the value within the text
+    pub range: Range<usize>,
+    /// the value with all but the last 4 characters masked
+    pub masked: String,
+}
+
+/// Every finding of a document
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PiiReport {
+    pub findings: Vec<PiiFinding>,
+}
+
+impl PiiReport {
+    /// risk of the worst finding, `None` if nothing was found
+    pub fn risk(&self) -> Option<Risk> {
+        self.findings.iter().map(|f| f.risk).max()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    pub fn contains(&self, kind: PiiKind) -> bool {
+        self.findings.iter().any(|f| f.kind == kind)
+    }
+
+    /// findings of at least the given risk
+    pub fn at_least(&self, risk: Risk) -> impl Iterator<Item = &PiiFinding> {
+        self.findings.iter().filter(move |f| f.risk >= risk)
+    }
+
+    /// Redactions of the OCR findings of at least the given risk,
+    /// `doc` must be the document that was scanned
+    pub fn redactions(&self, doc: &ParsedDoc, risk: Risk) -> Vec<Redaction> {
+        let mut out = Vec::new();
+        for f in self.at_least(risk) {
+            if let PiiSource::Ocr { text_idx } = f.source {
+                out.extend(doc.redactions(f.kind.name(), [(text_idx, f.range.clone())]));
+            }
+        }
+        out
+    }
+
+    /// Redactions of the pdf findings of at least the given risk,
+    /// to be painted by `Redactor::redact_pdf` on the document that was scanned
+    pub fn pdf_redactions(&self, risk: Risk) -> Vec<Redaction> {
+        self.at_least(risk)
+            .filter_map(|f| {
+                let source = match f.source {
+                    PiiSource::PdfImage { img_idx, text_idx } => {
+                        RedactSource::PdfImage { img_idx, text_idx }
+                    }
+                    PiiSource::TextLayer { .. } => RedactSource::TextLayer,
+                    PiiSource::Ocr { .. } => return None,
+                };
+                Some(Redaction {
+                    kind: f.kind.name().to_owned(),
+                    page_no: f.page_no?,
+                    source,
+                    range: f.range.clone(),
+                    masked: f.masked.clone(),
+                    rect: None,
+                })
+            })
+            .collect()
+    }
+}
+
+fn re(s: &str) -> Regex {
+    Regex::new(s).expect("invalid pii regex")
+}
+
+static CARD: LazyLock<Regex> = LazyLock::new(|| re(r"\b(?:\d[ -]?){12,18}\d\b"));
+static SSN: LazyLock<Regex> = LazyLock::new(|| re(r"\b(\d{3})([- ]?)(\d{2})([- ]?)(\d{4})\b"));
+static DIGITS: LazyLock<Regex> = LazyLock::new(|| re(r"\b\d(?:[ -]?\d){5,16}\b"));
+static IBAN: LazyLock<Regex> =
+    LazyLock::new(|| re(r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b"));
+static DATE: LazyLock<Regex> = LazyLock::new(|| {
+    re(
+        r"(?i)\b(?:\d{1,2}[/.-]\d{1,2}[/.-]\d{2,4}|\d{4}-\d{2}-\d{2}|\d{1,2} [a-z]{3,9}\.? \d{4}|[a-z]{3,9}\.? \d{1,2},? \d{4})\b",
+    )
+});
+static EMAIL: LazyLock<Regex> =
+    LazyLock::new(|| re(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b"));
+static PHONE: LazyLock<Regex> =
+    LazyLock::new(|| re(r"(?:\+\d{1,3}[-. ]?)?(?:\(\d{2,4}\)|\b\d{3})[-. ]?\d{3}[-. ]?\d{4}\b"));
+
+/// words that must appear near a number for it to count as
+/// one of these, the numbers alone are too common on invoices
+const ROUTING_WORDS: &[&str] = &["routing", "aba", "rtn", "transit"];
+const ACCOUNT_WORDS: &[&str] = &["account", "acct", "a/c", "iban", "cuenta"];
+/// "Acct #" on an invoice is the customer account,
+/// an account number also needs one of these to count
+const BANK_WORDS: &[&str] = &[
+    "bank", "iban", "routing", "checking", "savings", "swift", "wire",
+];
+const SSN_WORDS: &[&str] = &["ssn", "social security", "ss#", "soc sec"];
+const DOB_WORDS: &[&str] = &[
+    "dob",
+    "d.o.b",
+    "birth",
+    "birthdate",
+    "birthday",
+    "born",
+    "nacimiento",
+];
+
+/// true if one of the words is in the context as a whole word,
+/// so "aba" is not found in "alabama" nor "born" in "borne"
+fn near(context: &str, words: &[&str]) -> bool {
+    let edge = |c: Option<char>| !c.is_some_and(char::is_alphanumeric);
+    words.iter().any(|w| {
+        context.match_indices(w).any(|(i, _)| {
+            edge(context[..i].chars().next_back()) && edge(context[i + w.len()..].chars().next())
+        })
+    })
+}
+
+/// ABA routing number checksum
+fn aba(digits: &[u32]) -> bool {
+    digits.len() == 9
+        && (3 * (digits[0] + digits[3] + digits[6])
+            + 7 * (digits[1] + digits[4] + digits[7])
+            + digits[2]
+            + digits[5]
+            + digits[8])
+            .is_multiple_of(10)
+}
+
+/// IBAN mod 97 checksum
+fn iban(s: &str) -> bool {
+    let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    let rearranged = s.chars().skip(4).chain(s.chars().take(4));
+    let mut rem = 0u32;
+    for c in rearranged {
+        let Some(v) = c.to_digit(36) else {
+            return false;
+        };
+        rem = match v {
+            0..=9 => (rem * 10 + v) % 97,
+            _ => (rem * 100 + v) % 97,
+        };
+    }
+    rem == 1
+}
+
+/// area, group and serial numbers that are never issued
+fn valid_ssn(area: &str, group: &str, serial: &str) -> bool {
+    area != "000" && area != "666" && !area.starts_with('9') && group != "00" && serial != "0000"
+}
+
+/// adds the finding unless the value was already reported
+fn push(found: &mut Vec<(PiiKind, Range<usize>)>, kind: PiiKind, range: Range<usize>) {
+    if !found
+        .iter()
+        .any(|(_, t)| t.start < range.end && range.start < t.end)
+    {
+        found.push((kind, range));
+    }
+}
+
+/// Scans a single text. `context` is the lower case text around it,
+/// used to tell numbers that need a label apart (SSN, accounts, dates of birth)
+pub fn scan(text: &str, context: &str) -> Vec<(PiiKind, Range<usize>)> {
+    let mut found = Vec::new();
+
+    // most specific first, a value is only reported once
+    for m in EMAIL.find_iter(text) {
+        push(&mut found, PiiKind::Email, m.range());
+    }
+    for m in CARD.find_iter(text).filter(|m| luhn(m.as_str())) {
+        push(&mut found, PiiKind::CardNumber, m.range());
+    }
+    for c in SSN.captures_iter(text) {
+        let sep = (&c[2], &c[4]);
+        let dashed = sep == ("-", "-") || sep == (" ", " ");
+        if valid_ssn(&c[1], &c[3], &c[5]) && (dashed || near(context, SSN_WORDS)) {
+            push(&mut found, PiiKind::Ssn, c.get(0).unwrap().range());
+        }
+    }
+    for m in IBAN.find_iter(text).filter(|m| iban(m.as_str())) {
+        push(&mut found, PiiKind::BankAccount, m.range());
+    }
+    // before accounts so the phone of a bank is not taken for its account
+    for m in PHONE.find_iter(text) {
+        push(&mut found, PiiKind::Phone, m.range());
+    }
+    for m in DIGITS.find_iter(text) {
+        let digits: Vec<u32> = m.as_str().chars().filter_map(|c| c.to_digit(10)).collect();
+        if aba(&digits) && near(context, ROUTING_WORDS) {
+            push(&mut found, PiiKind::RoutingNumber, m.range());
+        } else if near(context, ACCOUNT_WORDS) && near(context, BANK_WORDS) {
+            push(&mut found, PiiKind::BankAccount, m.range());
+        }
+    }
+    if near(context, DOB_WORDS) {
+        for m in DATE.find_iter(text) {
+            push(&mut found, PiiKind::DateOfBirth, m.range());
+        }
+    }
+
+    found.sort_by_key(|(_, r)| r.start);
+    found
+}
+
+fn finding(
+    text: &str,
+    kind: PiiKind,
+    range: Range<usize>,
+    source: PiiSource,
+    page_no: Option<usize>,
+) -> PiiFinding {
+    PiiFinding {
+        kind,
+        risk: kind.risk(),
+        source,
+        page_no,
+        masked: mask(&text[range.clone()]),
+        range,
+    }
+}
+
+impl ParsedDoc {
+    /// Scans every text for personal data.
+    /// labels are often a text of their own, so the previous
+    /// text is used as context alongside the text itself
+    pub fn detect_pii(&self) -> PiiReport {
+        let mut findings = Vec::new();
+        for (i, t) in self.texts.iter().enumerate() {
+            let prev = i
+                .checked_sub(1)
+                .and_then(|p| self.texts.get(p))
+                .map(|p| p.text.as_str())
+                .unwrap_or_default();
+            let context = format!("{prev}\n{}", t.text).to_lowercase();
+            let page_no = t.prov.first().map(|p| p.page_no);
+
+            findings.extend(scan(&t.text, &context).into_iter().map(|(kind, range)| {
+                finding(
+                    &t.text,
+                    kind,
+                    range,
+                    PiiSource::Ocr { text_idx: i },
+                    page_no,
+                )
+            }));
+        }
+        PiiReport { findings }
+    }
+}
+
+impl PdfDoc {
+    /// Scans the ocred images and the text layer of every page.
+    /// findings of the images are reported by image and on the page of the pdf
+    /// the image is placed on, see `PiiReport::pdf_redactions`
+    pub fn detect_pii(&self) -> OcrResult<PiiReport> {
+        let mut report = PiiReport::default();
+
+        let doc = self.load()?;
+        for (img_idx, (page_idx, _)) in image_placements(&doc)?.into_iter().enumerate() {
+            let Some(Ok(parsed)) = self.parsed_doc.get(img_idx) else {
+                continue;
+            };
+            report
+                .findings
+                .extend(parsed.detect_pii().findings.into_iter().map(|f| {
+                    let PiiSource::Ocr { text_idx } = f.source else {
+                        return f;
+                    };
+                    PiiFinding {
+                        source: PiiSource::PdfImage { img_idx, text_idx },
+                        page_no: Some(page_idx as usize + 1),
+                        ..f
+                    }
+                }));
+        }
+
+        for (idx, page) in doc.pages().iter().enumerate() {
+            let text = page.text()?.all();
+            for line in text.lines() {
+                // lines are scanned on their own to keep the context local
+                let offset = line.as_ptr() as usize - text.as_ptr() as usize;
+                let context = line.to_lowercase();
+                report
+                    .findings
+                    .extend(scan(line, &context).into_iter().map(|(kind, range)| {
+                        let source = PiiSource::TextLayer {
+                            page_idx: idx as u16,
+                        };
+                        let range = range.start + offset..range.end + offset;
+                        finding(&text, kind, range, source, Some(idx + 1))
+                    }));
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[test]
+fn pii() {
+    let doc: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [
+            { "text": "Guest: Jane Roe, jane.roe@example.com, +1 555-010-4477", "prov": [] },
+            { "text": "Card 4111 1111 1111 1111 exp 04/27", "prov": [] },
+            { "text": "Invoice 4111 1111 1111 1112", "prov": [] },
+            { "text": "SSN 078-05-1120 DOB 12/05/1984", "prov": [] },
+            { "text": "Routing Number", "prov": [] },
+            { "text": "021000021", "prov": [] },
+            { "text": "Checking account no. 000123456789", "prov": [] },
+            { "text": "IBAN DE89 3704 0044 0532 0130 00", "prov": [] },
+            { "text": "Date 12/05/2024 Total 1,234.00", "prov": [] },
+            // the customer account of an invoice
+            { "text": "Acct # 4471029 Net 30", "prov": [] },
+            { "text": "Bank account support 555-010-4477", "prov": [] },
+            { "text": "Thank you", "prov": [] },
+            // a bank alone is no account
+            { "text": "Pay at any bank branch, ref 88120344", "prov": [] },
+            // the words count as whole words only
+            { "text": "Alabama database 021000021", "prov": [] },
+            { "text": "Air-borne freight 12/05/2024", "prov": [] },
+        ],
+    }))
+    .unwrap();
+
+    let report = doc.detect_pii();
+    let kinds: Vec<_> = report.findings.iter().map(|f| (f.source, f.kind)).collect();
+    let ocr = |text_idx| PiiSource::Ocr { text_idx };
+    assert_eq!(
+        kinds,
+        vec![
+            (ocr(0), PiiKind::Email),
+            (ocr(0), PiiKind::Phone),
+            (ocr(1), PiiKind::CardNumber),
+            (ocr(3), PiiKind::Ssn),
+            (ocr(3), PiiKind::DateOfBirth),
+            (ocr(5), PiiKind::RoutingNumber),
+            (ocr(6), PiiKind::BankAccount),
+            (ocr(7), PiiKind::BankAccount),
+            (ocr(10), PiiKind::Phone),
+        ]
+    );
+    assert_eq!(report.risk(), Some(Risk::Critical));
+    assert_eq!(report.findings[2].masked, "**** **** **** 1111");
+    // the texts have no boxes, there is nothing to paint
+    assert!(report.redactions(&doc, Risk::Critical).is_empty());
+    assert_eq!(report.at_least(Risk::High).count(), 5);
+    // findings of a `ParsedDoc` can only be painted on its own image
+    assert!(report.pdf_redactions(Risk::Low).is_empty());
+
+    let card = PiiFinding {
+        source: PiiSource::PdfImage {
+            img_idx: 0,
+            text_idx: 1,
+        },
+        page_no: Some(2),
+        ..report.findings[2].clone()
+    };
+    let redactions = PiiReport {
+        findings: vec![card],
+    }
+    .pdf_redactions(Risk::High);
+    assert_eq!(redactions.len(), 1);
+    assert_eq!(
+        redactions[0].source,
+        RedactSource::PdfImage {
+            img_idx: 0,
+            text_idx: 1
+        }
+    );
+    assert_eq!(redactions[0].page_no, 2);
+}
@@ -0,0 +1,608 @@
+/// Redaction: paints opaque boxes over sensitive text
+/// found by the OCR so a redacted copy can be stored
+use std::ops::Range;
+
+use image::{DynamicImage, Rgba};
+use pdf::prelude::*;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    OcrErrs,
+    err::OcrResult,
+    overlay::{PixelRect, fill_rect},
+    pdf::{
+        doc::{PdfDoc, render_config},
+        searchable::{PointRect, image_placements},
+    },
+    search::fuzzy::HitSource,
+    server::docling::{BoundingBox, PageSize, ParsedDoc},
+};
+
+/// What to look for in the text of the document
+#[derive(Debug, Clone)]
+pub enum RedactPattern {
+    /// payment card numbers, validated with the Luhn checksum
+    Pan,
+    /// US social security numbers
+    Ssn,
+    /// phone numbers
+    Phone,
+    /// anything matching the regex, reported under the given name
+    Custom { name: String, re: Regex },
+}
+
+impl RedactPattern {
+    pub fn custom<S: Into<String>>(name: S, re: &str) -> OcrResult<Self> {
+        Ok(Self::Custom {
+            name: name.into(),
+            re: Regex::new(re)?,
+        })
+    }
+
+    /// name reported in the audit
+    pub fn name(&self) -> &str {
+        match self {
+            RedactPattern::Pan => "pan",
+            RedactPattern::Ssn => "ssn",
+            RedactPattern::Phone => "phone",
+            RedactPattern::Custom { name, .. } => name,
+        }
+    }
+
+    fn regex(&self) -> OcrResult<Regex> {
+        let re = match self {
+            RedactPattern::Pan => r"\b(?:\d[ -]?){12,18}\d\b",
+            RedactPattern::Ssn => r"\b\d{3}-\d{2}-\d{4}\b|\b\d{3} \d{2} \d{4}\b",
+            RedactPattern::Phone => {
+                r"(?:\+?\b1[-. ]?)?(?:\(\d{3}\)|\b\d{3})[-. ]?\d{3}[-. ]?\d{4}\b"
+            }
+            RedactPattern::Custom { re, .. } => return Ok(re.clone()),
+        };
+        Ok(Regex::new(re)?)
+    }
+
+    /// extra validation of a match
+    fn accept(&self, matched: &str) -> bool {
+        match self {
+            RedactPattern::Pan => luhn(matched),
+            _ => true,
+        }
+    }
+}
+
+/// Luhn checksum of the digits of `s`, other characters are ignored
+pub fn luhn(s: &str) -> bool {
+    let digits: Vec<u32> = s.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() < 2 {
+        return false;
+    }
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match i % 2 {
+            0 => d,
+            _ if d * 2 > 9 => d * 2 - 9,
+            _ => d * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// hides everything but the last 4 letters or digits
+pub fn mask(value: &str) -> String {
+    let keep = value.chars().filter(|c| c.is_alphanumeric()).count();
+    let mut hidden = keep.saturating_sub(4);
+    value
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() && hidden > 0 {
+                hidden -= 1;
+                '*'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Where the redacted text was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "source")]
+pub enum RedactSource {
+    /// an `OcrText` of a `ParsedDoc`, by index into `ParsedDoc::texts`
+    Ocr { text_idx: usize },
+    /// an `OcrText` of an embedded image of a pdf,
+    /// by index into `PdfDoc::imgs` and into the `ParsedDoc::texts` of the image
+    PdfImage { img_idx: usize, text_idx: usize },
+    /// the text layer of the pdf page
+    TextLayer,
+}
+
+/// A region to redact and, once painted, its audit entry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Redaction {
+    /// name of the pattern or of the search that found it
+    pub kind: String,
+    /// page of the `ParsedDoc`, or of the pdf for the pdf sources
+    pub page_no: usize,
+    #[serde(flatten)]
+    pub source: RedactSource,
+    /// byte range of the match within the `OcrText::text`,
+    /// or within the text of the page for the text layer
+    pub range: Range<usize>,
+    /// the removed value with all but the last 4 characters masked
+    pub masked: String,
+    /// where it was painted, `None` until it is painted
+    pub rect: Option<PixelRect>,
+}
+
+impl ParsedDoc {
+    /// Redactions for search hits, one per box of the hit text.
+    /// accepts `(text_idx, range)` pairs i.e. from `PatternHit` or `FuzzyHit`
+    pub fn redactions<I: IntoIterator<Item = (usize, Range<usize>)>>(
+        &self,
+        kind: &str,
+        hits: I,
+    ) -> Vec<Redaction> {
+        let mut out = Vec::new();
+        for (text_idx, range) in hits {
+            let Some(text) = self.texts.get(text_idx) else {
+                continue;
+            };
+            let masked = mask(text.text.get(range.clone()).unwrap_or_default());
+
+            out.extend(text.prov.iter().map(|p| Redaction {
+                kind: kind.to_owned(),
+                page_no: p.page_no,
+                source: RedactSource::Ocr { text_idx },
+                range: range.clone(),
+                masked: masked.clone(),
+                rect: None,
+            }));
+        }
+        out
+    }
+}
+
+/// The redacted document and what was removed from it
+#[derive(Debug, Clone)]
+pub struct RedactedPdf {
+    /// an image only PDF, the original text layer is not kept
+    pub bytes: Vec<u8>,
+    /// one entry per painted box
+    pub audit: Vec<Redaction>,
+}
+
+/// maps a rectangle in points onto the rendered image of the page
+fn to_pixels(rect: PointRect, page: PageSize, width: u32, height: u32) -> Option<PixelRect> {
+    let bbox = BoundingBox {
+        t: rect.top as f64,
+        l: rect.left as f64,
+        r: rect.right as f64,
+        b: rect.bottom as f64,
+        coord_origin: "BOTTOMLEFT".into(),
+    };
+    PixelRect::from_bbox(&bbox, Some(page), width, height)
+}
+
+/// every place the value is written in the text layer of the page
+fn text_layer_rects(text: &PdfPageText, value: &str) -> Vec<PointRect> {
+    let search = text.search(value, &PdfSearchOptions::new().match_case(true));
+    search
+        .iter(PdfSearchDirection::SearchForward)
+        .flat_map(|segments| {
+            segments
+                .iter()
+                .map(|s| PointRect::from(s.bounds()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Finds and paints redactions
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    patterns: Vec<(RedactPattern, Regex)>,
+    /// pixels added around every box
+    pub padding: u32,
+    pub color: Rgba<u8>,
+    /// paint the whole box of the text instead of
+    /// estimating where the match sits within it
+    pub whole_box: bool,
+}
+
+impl Redactor {
+    pub fn new<I: IntoIterator<Item = RedactPattern>>(patterns: I) -> OcrResult<Self> {
+        let patterns = patterns
+            .into_iter()
+            .map(|p| {
+                let re = p.regex()?;
+                Ok((p, re))
+            })
+            .collect::<OcrResult<_>>()?;
+
+        Ok(Self {
+            patterns,
+            padding: 2,
+            color: Rgba([0, 0, 0, 255]),
+            whole_box: true,
+        })
+    }
+
+    pub fn padding(mut self, px: u32) -> Self {
+        self.padding = px;
+        self
+    }
+
+    /// the alpha channel is ignored, redactions are always opaque
+    pub fn color(mut self, color: Rgba<u8>) -> Self {
+        self.color = Rgba([color[0], color[1], color[2], 255]);
+        self
+    }
+
+    pub fn whole_box(mut self, whole_box: bool) -> Self {
+        self.whole_box = whole_box;
+        self
+    }
+
+    /// every match of every pattern in the text as `(pattern name, byte range)`
+    fn hits<'a>(&'a self, text: &'a str) -> impl Iterator<Item = (&'a str, Range<usize>)> + 'a {
+        self.patterns.iter().flat_map(move |(pattern, re)| {
+            re.find_iter(text)
+                .filter(|m| pattern.accept(m.as_str()))
+                .map(move |m| (pattern.name(), m.range()))
+        })
+    }
+
+    /// every match of every pattern in the document
+    pub fn find(&self, doc: &ParsedDoc) -> Vec<Redaction> {
+        let mut out = Vec::new();
+        for (pattern, re) in &self.patterns {
+            let hits = doc.texts.iter().enumerate().flat_map(|(i, t)| {
+                re.find_iter(&t.text)
+                    .filter(|m| pattern.accept(m.as_str()))
+                    .map(move |m| (i, m.range()))
+            });
+            out.extend(doc.redactions(pattern.name(), hits));
+        }
+        out
+    }
+
+    /// Every match of every pattern in the text layer and in the ocred
+    /// images of the pdf. Fails if an image has no OCR result,
+    /// as the text it holds cannot be searched
+    pub fn find_pdf(&self, pdf: &PdfDoc) -> OcrResult<Vec<Redaction>> {
+        let doc = pdf.load()?;
+        let mut out = Vec::new();
+
+        for (img_idx, (page_idx, _)) in image_placements(&doc)?.into_iter().enumerate() {
+            let Some(Ok(parsed)) = pdf.parsed_doc.get(img_idx) else {
+                return Err(OcrErrs::NotOcred(img_idx));
+            };
+
+            for (text_idx, t) in parsed.texts.iter().enumerate() {
+                out.extend(self.hits(&t.text).map(|(kind, range)| Redaction {
+                    kind: kind.to_owned(),
+                    page_no: page_idx as usize + 1,
+                    source: RedactSource::PdfImage { img_idx, text_idx },
+                    masked: mask(&t.text[range.clone()]),
+                    range,
+                    rect: None,
+                }));
+            }
+        }
+
+        for (idx, page) in doc.pages().iter().enumerate() {
+            let text = page.text()?.all();
+            out.extend(self.hits(&text).map(|(kind, range)| Redaction {
+                kind: kind.to_owned(),
+                page_no: idx + 1,
+                source: RedactSource::TextLayer,
+                masked: mask(&text[range.clone()]),
+                range,
+                rect: None,
+            }));
+        }
+
+        Ok(out)
+    }
+
+    /// the box of the text, narrowed down to the match unless `Self::whole_box`
+    fn match_box(
+        &self,
+        text: &str,
+        bbox: &BoundingBox,
+        range: &Range<usize>,
+    ) -> Option<BoundingBox> {
+        let len = text.chars().count();
+        if self.whole_box || len == 0 {
+            return Some(bbox.clone());
+        }
+
+        // assumes evenly spaced characters
+        let start = text.get(..range.start)?.chars().count();
+        let end = text.get(..range.end)?.chars().count();
+        let (l, r) = (bbox.l.min(bbox.r), bbox.l.max(bbox.r));
+        let w = (r - l) / len as f64;
+        Some(BoundingBox {
+            l: l + start as f64 * w,
+            r: l + end as f64 * w,
+            ..bbox.clone()
+        })
+    }
+
+    /// where the redaction sits on an image of the page
+    fn locate(&self, doc: &ParsedDoc, r: &Redaction, width: u32, height: u32) -> Option<PixelRect> {
+        let RedactSource::Ocr { text_idx } = r.source else {
+            return None;
+        };
+        let text = doc.texts.get(text_idx)?;
+        let prov = text.prov.iter().find(|p| p.page_no == r.page_no)?;
+        let page = doc.pages.get(&r.page_no).map(|p| p.size);
+        let bbox = self.match_box(&text.text, &prov.bbox, &r.range)?;
+        let rect = PixelRect::from_bbox(&bbox, page, width, height)?;
+
+        Some(rect.pad(self.padding, width, height))
+    }
+
+    /// where an ocred text of an embedded image sits on the page
+    fn locate_in_image(
+        &self,
+        pdf: &PdfDoc,
+        img_idx: usize,
+        text_idx: usize,
+        range: &Range<usize>,
+        area: PointRect,
+    ) -> Vec<PointRect> {
+        let Some(Ok(parsed)) = pdf.parsed_doc.get(img_idx) else {
+            return Vec::new();
+        };
+        let Some(text) = parsed.texts.get(text_idx) else {
+            return Vec::new();
+        };
+        let img = pdf.imgs.get(img_idx).map(|i| PageSize {
+            width: i.width() as f64,
+            height: i.height() as f64,
+        });
+
+        text.prov
+            .iter()
+            .filter_map(|p| {
+                let bbox = self.match_box(&text.text, &p.bbox, range)?;
+                let size = parsed.pages.get(&p.page_no).map(|p| p.size).or(img)?;
+                PointRect::from_bbox(&bbox, size, area)
+            })
+            .collect()
+    }
+
+    /// Paints the redactions of the given page on the image,
+    /// returns the redacted image and the audit of the page.
+    /// Fails if a redaction cannot be located on the image
+    pub fn redact_image(
+        &self,
+        img: &DynamicImage,
+        doc: &ParsedDoc,
+        page_no: usize,
+        redactions: &[Redaction],
+    ) -> OcrResult<(DynamicImage, Vec<Redaction>)> {
+        let mut out = img.to_rgba8();
+        let (width, height) = out.dimensions();
+
+        let mut audit = Vec::new();
+        for r in redactions.iter().filter(|r| r.page_no == page_no) {
+            let rect = self
+                .locate(doc, r, width, height)
+                .ok_or_else(|| not_located(r))?;
+            fill_rect(&mut out, rect, self.color);
+            audit.push(Redaction {
+                rect: Some(rect),
+                ..r.clone()
+            });
+        }
+
+        Ok((DynamicImage::ImageRgba8(out), audit))
+    }
+
+    /// Renders every page of the PDF, paints the redactions
+    /// and builds a new PDF from the images.
+    /// redactions must come from `Self::find_pdf`, `PdfDoc::redactions`
+    /// or `PiiReport::pdf_redactions`, the whole document fails
+    /// if any of them cannot be located
+    pub fn redact_pdf(&self, pdf: &PdfDoc, redactions: &[Redaction]) -> OcrResult<RedactedPdf> {
+        let src = pdf.load()?;
+        let placements = image_placements(&src)?;
+        let pages = src.pages().len() as usize;
+        if let Some(r) = redactions.iter().find(|r| {
+            matches!(r.source, RedactSource::Ocr { .. }) || r.page_no == 0 || r.page_no > pages
+        }) {
+            return Err(not_located(r));
+        }
+
+        let mut out = pdf.pdfium.create_new_pdf()?;
+        let mut audit = Vec::new();
+
+        for (idx, page) in src.pages().iter().enumerate() {
+            let mut img = page
+                .render_with_config(&render_config())?
+                .as_image()
+                .to_rgba8();
+            let (width, height) = img.dimensions();
+            let (w, h) = (page.width(), page.height());
+            let size = PageSize {
+                width: w.value as f64,
+                height: h.value as f64,
+            };
+            let text = page.text()?;
+            let page_text = text.all();
+
+            for r in redactions.iter().filter(|r| r.page_no == idx + 1) {
+                let rects = match r.source {
+                    RedactSource::PdfImage { img_idx, text_idx } => placements
+                        .get(img_idx)
+                        .filter(|(page_idx, _)| *page_idx as usize == idx)
+                        .map(|(_, area)| {
+                            self.locate_in_image(pdf, img_idx, text_idx, &r.range, *area)
+                        })
+                        .unwrap_or_default(),
+                    RedactSource::TextLayer => page_text
+                        .get(r.range.clone())
+                        .map(|value| text_layer_rects(&text, value))
+                        .unwrap_or_default(),
+                    RedactSource::Ocr { .. } => Vec::new(),
+                };
+
+                let rects: Vec<_> = rects
+                    .into_iter()
+                    .filter_map(|rect| to_pixels(rect, size, width, height))
+                    .map(|rect| rect.pad(self.padding, width, height))
+                    .collect();
+                if rects.is_empty() {
+                    return Err(not_located(r));
+                }
+
+                for rect in rects {
+                    fill_rect(&mut img, rect, self.color);
+                    audit.push(Redaction {
+                        rect: Some(rect),
+                        ..r.clone()
+                    });
+                }
+            }
+
+            let img = DynamicImage::ImageRgba8(img);
+            let obj = PdfPageImageObject::new_with_size(&out, &img, w, h)?;
+            let mut new_page = out
+                .pages_mut()
+                .create_page_at_end(PdfPagePaperSize::from_points(w, h))?;
+            new_page.objects_mut().add_image_object(obj)?;
+        }
+
+        Ok(RedactedPdf {
+            bytes: out.save_to_bytes()?,
+            audit,
+        })
+    }
+}
+
+fn not_located(r: &Redaction) -> OcrErrs {
+    OcrErrs::RedactionNotLocated {
+        kind: r.kind.clone(),
+        page_no: r.page_no,
+    }
+}
+
+impl PdfDoc {
+    /// Redactions for hits already located in the pdf, i.e. from `Self::fuzzy_search`.
+    /// accepts `(source, text_idx, range)`: for an image the index into the texts of its
+    /// `ParsedDoc`, for the text layer the index of the line in the text of the page.
+    /// Fails if an image was not ocred or a hit is not in the document
+    pub fn redactions<I: IntoIterator<Item = (HitSource, usize, Range<usize>)>>(
+        &self,
+        kind: &str,
+        hits: I,
+    ) -> OcrResult<Vec<Redaction>> {
+        let doc = self.load()?;
+        let placements = image_placements(&doc)?;
+        let mut out = Vec::new();
+
+        for (source, text_idx, range) in hits {
+            let (page_no, source, range, value) = match source {
+                HitSource::Image(img_idx) => {
+                    let Some(Ok(parsed)) = self.parsed_doc.get(img_idx) else {
+                        return Err(OcrErrs::NotOcred(img_idx));
+                    };
+                    let page_no = placements.get(img_idx).map_or(0, |(p, _)| *p as usize + 1);
+                    let value = parsed
+                        .texts
+                        .get(text_idx)
+                        .and_then(|t| t.text.get(range.clone()))
+                        .map(mask);
+                    let source = RedactSource::PdfImage { img_idx, text_idx };
+                    (page_no, source, range, value)
+                }
+                HitSource::TextLayer(page_idx) => {
+                    let text = doc.pages().get(page_idx)?.text()?.all();
+                    // the same lines as `PdfDoc::fuzzy_search`
+                    let line = text.lines().nth(text_idx).unwrap_or_default();
+                    let offset = line.as_ptr() as usize - text.as_ptr() as usize;
+                    let value = line.get(range.clone()).map(mask);
+                    let range = range.start + offset..range.end + offset;
+                    (page_idx as usize + 1, RedactSource::TextLayer, range, value)
+                }
+            };
+
+            let redaction = Redaction {
+                kind: kind.to_owned(),
+                page_no,
+                source,
+                range,
+                masked: value.unwrap_or_default(),
+                rect: None,
+            };
+            if redaction.masked.is_empty() || page_no == 0 {
+                return Err(not_located(&redaction));
+            }
+            out.push(redaction);
+        }
+        Ok(out)
+    }
+
+    /// redacts every match of the redactor in the text layer and the ocred
+    /// images of the document. `Self::ocr` must have been called first
+    pub fn redact(&self, redactor: &Redactor) -> OcrResult<RedactedPdf> {
+        let redactions = redactor.find_pdf(self)?;
+        redactor.redact_pdf(self, &redactions)
+    }
+}
+
+#[test]
+fn redact() {
+    use image::RgbaImage;
+
+    let doc: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [
+            { "text": "Card 4111 1111 1111 1111", "prov": [{ "page_no": 1, "charspan": [0, 24], "bbox": { "l": 0.0, "t": 10.0, "r": 96.0, "b": 20.0, "coord_origin": "TOPLEFT" } }] },
+            { "text": "Ref 4111 1111 1111 1112", "prov": [{ "page_no": 1, "charspan": [0, 23], "bbox": { "l": 0.0, "t": 40.0, "r": 92.0, "b": 50.0, "coord_origin": "TOPLEFT" } }] },
+            { "text": "SSN 078-05-1120, call (555) 010-4477", "prov": [{ "page_no": 1, "charspan": [0, 36], "bbox": { "l": 0.0, "t": 70.0, "r": 100.0, "b": 80.0, "coord_origin": "TOPLEFT" } }] },
+        ],
+    }))
+    .unwrap();
+
+    let redactor = Redactor::new([RedactPattern::Pan, RedactPattern::Ssn, RedactPattern::Phone])
+        .unwrap()
+        .padding(0)
+        .whole_box(false);
+    let found = redactor.find(&doc);
+    let kinds: Vec<_> = found.iter().map(|r| (r.kind.as_str(), r.source)).collect();
+    let ocr = |text_idx| RedactSource::Ocr { text_idx };
+    // the second card number fails the checksum
+    assert_eq!(
+        kinds,
+        vec![("pan", ocr(0)), ("ssn", ocr(2)), ("phone", ocr(2))]
+    );
+    assert_eq!(found[0].masked, "**** **** **** 1111");
+
+    let white = Rgba([255, 255, 255, 255]);
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(100, 100, white));
+    let (out, audit) = redactor.redact_image(&img, &doc, 1, &found).unwrap();
+    let out = out.to_rgba8();
+
+    assert!(audit.iter().all(|r| r.rect.is_some()));
+    assert_eq!(*out.get_pixel(50, 15), Rgba([0, 0, 0, 255]));
+    // "Card " and the unmatched number are left alone
+    assert_eq!(*out.get_pixel(5, 15), white);
+    assert_eq!(*out.get_pixel(50, 45), white);
+
+    // a hit without a box is an error, not a silent gap
+    let lost = Redaction {
+        source: ocr(9),
+        ..found[0].clone()
+    };
+    assert!(matches!(
+        redactor.redact_image(&img, &doc, 1, &[lost]),
+        Err(OcrErrs::RedactionNotLocated { .. })
+    ));
+}
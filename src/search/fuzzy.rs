@@ -0,0 +1,262 @@
+use std::ops::Range;
+
+use crate::server::docling::{OcrText, ParsedDoc};
+
+/// Characters that OCR commonly mistakes for one another.
+/// Each group is compared in lowercase, substituting a character
+/// for another one in the same group is cheaper than a regular edit
+const CONFUSIONS: &[&[char]] = &[
+    &['0', 'o', 'd', 'q'],
+    &['1', 'l', 'i', '|', '!'],
+    &['5', 's'],
+    &['8', 'b'],
+    &['2', 'z'],
+    &['6', 'g'],
+];
+
+/// Controls how forgiving a fuzzy search is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FuzzyOptions {
+    /// minimum score (0.0 - 1.0) a hit needs in order to be returned.
+    /// the score is `1 - distance / needle_len`
+    pub min_score: f64,
+    /// cost of swapping two characters that OCR commonly confuses
+    /// (0/O, 1/l/I, 5/S...). Set it to `1.0` to disable
+    pub confusion_cost: f64,
+    /// ignore case while matching
+    pub case_insensitive: bool,
+    /// ignore whitespace in both the needle and the text
+    /// so "INV OICE" matches "INVOICE"
+    pub ignore_whitespace: bool,
+}
+
+impl Default for FuzzyOptions {
+    fn default() -> Self {
+        Self {
+            min_score: 0.75,
+            confusion_cost: 0.25,
+            case_insensitive: true,
+            ignore_whitespace: true,
+        }
+    }
+}
+
+impl FuzzyOptions {
+    /// Sets the minimum score a hit needs
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Sets the cost of swapping commonly confused characters
+    pub fn confusion_cost(mut self, cost: f64) -> Self {
+        self.confusion_cost = cost;
+        self
+    }
+
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    pub fn ignore_whitespace(mut self, ignore_whitespace: bool) -> Self {
+        self.ignore_whitespace = ignore_whitespace;
+        self
+    }
+
+    /// cost of substituting `a` with `b`
+    fn sub_cost(&self, a: char, b: char) -> f64 {
+        if a == b {
+            return 0.0;
+        }
+
+        let (la, lb) = (lower(a), lower(b));
+        if la == lb {
+            return if self.case_insensitive { 0.0 } else { 1.0 };
+        }
+
+        let confused = CONFUSIONS
+            .iter()
+            .any(|group| group.contains(&la) && group.contains(&lb));
+
+        if confused {
+            self.confusion_cost.min(1.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+/// A single fuzzy match
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyHit {
+    /// index of the `OcrText` in `ParsedDoc::texts`, see `HitSource`
+    /// for hits found in the text layer of a pdf
+    pub text_idx: usize,
+    /// byte range of the match within the `OcrText::text`
+    pub range: Range<usize>,
+    /// the matched text as it appears in the document
+    pub matched: String,
+    /// weighted edit distance between the needle and the match
+    pub distance: f64,
+    /// similarity score between 0.0 and 1.0, 1.0 being an exact match
+    pub score: f64,
+}
+
+/// Where a `PdfDoc::fuzzy_search` hit was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitSource {
+    /// index of the ocred image,
+    /// `FuzzyHit::text_idx` is the index in its `ParsedDoc::texts`
+    Image(usize),
+    /// 0 based index of the page,
+    /// `FuzzyHit::text_idx` is the line within the text layer of the page
+    TextLayer(u16),
+}
+
+impl ParsedDoc {
+    /// Performs a fuzzy search that tolerates OCR errors
+    /// such as "Svsco" for "Sysco" or "1NVOICE" for "INVOICE".
+    /// Hits are sorted by score, best first
+    pub fn fuzzy_search(&self, needle: &str, opts: &FuzzyOptions) -> Vec<FuzzyHit> {
+        let mut hits: Vec<_> = self
+            .texts
+            .iter()
+            .enumerate()
+            .flat_map(|(i, OcrText { text, .. })| {
+                fuzzy_find(text, needle, opts)
+                    .into_iter()
+                    .map(move |(range, distance, score)| FuzzyHit {
+                        text_idx: i,
+                        matched: text[range.clone()].to_owned(),
+                        range,
+                        distance,
+                        score,
+                    })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits
+    }
+
+    /// returns true if any text fuzzy matches the needle
+    /// using the default options
+    pub fn contains_fuzzy(&self, needle: &str) -> bool {
+        self.texts
+            .iter()
+            .any(|t| !fuzzy_find(&t.text, needle, &FuzzyOptions::default()).is_empty())
+    }
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// chars of `s` paired with their byte offsets,
+/// whitespace is dropped if requested
+fn chars(s: &str, ignore_whitespace: bool) -> Vec<(usize, char)> {
+    s.char_indices()
+        .filter(|(_, c)| !(ignore_whitespace && c.is_whitespace()))
+        .collect()
+}
+
+/// Finds approximate occurrences of `needle` within `haystack`.
+/// Returns the byte range, distance and score of every non overlapping
+/// match that satisfies `opts.min_score`
+pub fn fuzzy_find(
+    haystack: &str,
+    needle: &str,
+    opts: &FuzzyOptions,
+) -> Vec<(Range<usize>, f64, f64)> {
+    let needle: Vec<char> = chars(needle, opts.ignore_whitespace)
+        .into_iter()
+        .map(|(_, c)| c)
+        .collect();
+    let text = chars(haystack, opts.ignore_whitespace);
+
+    if needle.is_empty() || text.is_empty() {
+        return Vec::new();
+    }
+
+    let m = needle.len();
+    let max_dist = (1.0 - opts.min_score) * m as f64;
+
+    // Sellers' algorithm: edit distance where the match is allowed
+    // to start anywhere in the text. `start` tracks where the
+    // alignment ending in each cell began.
+    let mut prev: Vec<f64> = (0..=m).map(|i| i as f64).collect();
+    let mut prev_start = vec![0usize; m + 1];
+    let mut col = vec![0.0; m + 1];
+    let mut col_start = vec![0usize; m + 1];
+
+    let mut candidates = Vec::new();
+
+    for j in 1..=text.len() {
+        let tc = text[j - 1].1;
+        col[0] = 0.0;
+        col_start[0] = j;
+
+        for i in 1..=m {
+            let sub = prev[i - 1] + opts.sub_cost(needle[i - 1], tc);
+            let ins = prev[i] + 1.0;
+            let del = col[i - 1] + 1.0;
+
+            (col[i], col_start[i]) = if sub <= ins && sub <= del {
+                (sub, prev_start[i - 1])
+            } else if ins <= del {
+                (ins, prev_start[i])
+            } else {
+                (del, col_start[i - 1])
+            };
+        }
+
+        if col[m] <= max_dist + f64::EPSILON && col_start[m] < j {
+            candidates.push((col_start[m], j, col[m]));
+        }
+
+        std::mem::swap(&mut prev, &mut col);
+        std::mem::swap(&mut prev_start, &mut col_start);
+    }
+
+    // keep the best non overlapping candidates, prefer lower
+    // distance then the longer span
+    candidates.sort_by(|a, b| a.2.total_cmp(&b.2).then((b.1 - b.0).cmp(&(a.1 - a.0))));
+
+    let mut taken: Vec<(usize, usize, f64)> = Vec::new();
+    for cand in candidates {
+        if taken.iter().all(|t| cand.1 <= t.0 || cand.0 >= t.1) {
+            taken.push(cand);
+        }
+    }
+    taken.sort_by_key(|t| t.0);
+
+    taken
+        .into_iter()
+        .map(|(s, e, dist)| {
+            let start = text[s].0;
+            let (last_idx, last) = text[e - 1];
+            let range = start..last_idx + last.len_utf8();
+            (range, dist, 1.0 - dist / m as f64)
+        })
+        .collect()
+}
+
+#[test]
+fn ocr_errors() {
+    let opts = FuzzyOptions::default();
+
+    let hits = fuzzy_find("Svsco Food Services", "Sysco", &opts);
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].0, 0..5);
+
+    let hits = fuzzy_find("1NVOICE #4432", "INVOICE", &opts);
+    assert_eq!(hits[0].0, 0..7);
+    assert!(hits[0].2 > 0.95);
+
+    let hits = fuzzy_find("Total: 5UB T0TAL", "subtotal", &opts);
+    assert_eq!(hits[0].0, 7..16);
+
+    assert!(fuzzy_find("Golden Waffles", "Sysco", &opts).is_empty());
+    assert!(fuzzy_find("Sysc0", "Sysco", &opts.confusion_cost(1.0).min_score(0.9)).is_empty());
+}
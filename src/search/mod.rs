@@ -0,0 +1,6 @@
+/// Text search helpers that work on top of the
+/// OCR results returned by the server.
+/// OCR output is rarely perfect so the searches in here
+/// are meant to be more forgiving than a plain `contains`
+pub mod fuzzy;
+pub mod pattern;
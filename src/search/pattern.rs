@@ -0,0 +1,238 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+
+use crate::{
+    err::OcrResult,
+    server::docling::{OcrText, ParsedDoc},
+};
+
+/// true unless both sides of the position are word characters
+const WORD_EDGE: &str = r"(?:\b{start-half}|\b{end-half})";
+
+/// A single search pattern.
+/// Literals are escaped before being compiled so vendor names
+/// such as "A+ Plumbing (NY)" are matched as written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    Literal(String),
+    Regex(String),
+}
+
+impl Pattern {
+    pub fn literal<S: Into<String>>(s: S) -> Self {
+        Self::Literal(s.into())
+    }
+
+    pub fn regex<S: Into<String>>(s: S) -> Self {
+        Self::Regex(s.into())
+    }
+
+    pub(crate) fn source(&self, whole_word: bool) -> String {
+        let src = match self {
+            Pattern::Literal(s) => regex::escape(s),
+            Pattern::Regex(s) => s.clone(),
+        };
+
+        // `\b` needs a word character on one side, so it never matches
+        // between a space and the ")" of "A+ Plumbing (NY)". Only reject
+        // positions that sit between two word characters instead
+        if whole_word {
+            format!(r"{WORD_EDGE}(?:{src}){WORD_EDGE}")
+        } else {
+            src
+        }
+    }
+}
+
+impl From<&str> for Pattern {
+    fn from(value: &str) -> Self {
+        Self::literal(value)
+    }
+}
+
+impl From<String> for Pattern {
+    fn from(value: String) -> Self {
+        Self::literal(value)
+    }
+}
+
+/// Options applied to every pattern of a `PatternSet`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub case_insensitive: bool,
+    /// only match when the ends of the match are not
+    /// glued to other word characters
+    pub whole_word: bool,
+}
+
+/// A precompiled set of patterns.
+/// Compile it once and reuse it to scan documents
+/// for many keywords in a single pass
+#[derive(Debug, Clone)]
+pub struct PatternSet {
+    patterns: Vec<Pattern>,
+    set: RegexSet,
+    regexes: Vec<Regex>,
+}
+
+impl PatternSet {
+    pub fn new<I, P>(patterns: I, opts: SearchOptions) -> OcrResult<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<Pattern>,
+    {
+        let patterns: Vec<Pattern> = patterns.into_iter().map(Into::into).collect();
+        let sources: Vec<String> = patterns.iter().map(|p| p.source(opts.whole_word)).collect();
+
+        let set = RegexSetBuilder::new(&sources)
+            .case_insensitive(opts.case_insensitive)
+            .build()?;
+
+        let regexes = sources
+            .iter()
+            .map(|src| {
+                RegexBuilder::new(src)
+                    .case_insensitive(opts.case_insensitive)
+                    .build()
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            patterns,
+            set,
+            regexes,
+        })
+    }
+
+    /// short hand for a case insensitive set of literals
+    pub fn literals<I, S>(needles: I) -> OcrResult<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::new(
+            needles.into_iter().map(|s| Pattern::Literal(s.into())),
+            SearchOptions {
+                case_insensitive: true,
+                whole_word: false,
+            },
+        )
+    }
+
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.patterns
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// indexes of the patterns that match the text
+    pub fn matches(&self, text: &str) -> Vec<usize> {
+        self.set.matches(text).into_iter().collect()
+    }
+
+    /// every occurrence of every pattern in the text
+    /// as `(pattern_idx, byte range)`
+    pub fn find_iter<'a>(
+        &'a self,
+        text: &'a str,
+    ) -> impl Iterator<Item = (usize, Range<usize>)> + 'a {
+        self.set.matches(text).into_iter().flat_map(move |idx| {
+            self.regexes[idx]
+                .find_iter(text)
+                .map(move |m| (idx, m.range()))
+        })
+    }
+}
+
+/// An occurrence of a pattern in a `ParsedDoc`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternHit {
+    /// index of the pattern in the `PatternSet`
+    pub pattern_idx: usize,
+    /// index of the `OcrText` in `ParsedDoc::texts`
+    pub text_idx: usize,
+    /// byte range of the match within the `OcrText::text`
+    pub range: Range<usize>,
+}
+
+impl ParsedDoc {
+    /// Searches every text for every pattern of the set.
+    /// texts that do not match any pattern are only scanned once
+    pub fn search(&self, set: &PatternSet) -> Vec<PatternHit> {
+        self.texts
+            .iter()
+            .enumerate()
+            .flat_map(|(text_idx, OcrText { text, .. })| {
+                set.find_iter(text)
+                    .map(move |(pattern_idx, range)| PatternHit {
+                        pattern_idx,
+                        text_idx,
+                        range,
+                    })
+            })
+            .collect()
+    }
+
+    /// indexes of the patterns that are found anywhere in this document
+    pub fn matching_patterns(&self, set: &PatternSet) -> Vec<usize> {
+        let mut found = vec![false; set.len()];
+        for OcrText { text, .. } in &self.texts {
+            for idx in set.set.matches(text).iter() {
+                found[idx] = true;
+            }
+        }
+
+        found
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, f)| f.then_some(i))
+            .collect()
+    }
+
+    /// perform a search using a regular expression
+    pub fn contains_regex(&self, re: &Regex) -> bool {
+        self.texts.iter().any(|t| re.is_match(&t.text))
+    }
+}
+
+#[test]
+fn literal_patterns() {
+    let set = PatternSet::literals(["A+ Plumbing (NY)", "sysco", "Total"]).unwrap();
+
+    assert_eq!(set.matches("Bill from A+ Plumbing (NY) Inc."), vec![0]);
+    assert!(set.matches("AA Plumbing NY").is_empty());
+
+    let hits: Vec<_> = set.find_iter("SYSCO total: 5.00").collect();
+    assert_eq!(hits, vec![(1, 0..5), (2, 6..11)]);
+
+    let set = PatternSet::new(
+        [Pattern::regex(r"INV-\d+"), Pattern::literal("net")],
+        SearchOptions {
+            case_insensitive: false,
+            whole_word: true,
+        },
+    )
+    .unwrap();
+    assert_eq!(set.matches("INV-1234 Internet"), vec![0]);
+
+    let set = PatternSet::new(
+        ["A+ Plumbing (NY)"],
+        SearchOptions {
+            case_insensitive: true,
+            whole_word: true,
+        },
+    )
+    .unwrap();
+    assert_eq!(set.matches("Bill from A+ Plumbing (NY)"), vec![0]);
+    assert_eq!(set.matches("a+ plumbing (ny), Albany"), vec![0]);
+    assert!(set.matches("AA+ Plumbing (NY)").is_empty());
+
+    assert!(PatternSet::new([Pattern::regex("(")], SearchOptions::default()).is_err());
+}
@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::docling::{OcrText, ParsedDoc};
+
+/// Quality grade assigned by Docling
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityGrade {
+    #[default]
+    Unspecified,
+    Poor,
+    Fair,
+    Good,
+    Excellent,
+}
+
+/// Confidence of the conversion of a single page.
+/// scores are between 0.0 and 1.0, `None` if the server could not compute it
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct PageConfidence {
+    #[serde(default)]
+    pub parse_score: Option<f64>,
+    #[serde(default)]
+    pub layout_score: Option<f64>,
+    #[serde(default)]
+    pub table_score: Option<f64>,
+    #[serde(default)]
+    pub ocr_score: Option<f64>,
+    #[serde(default)]
+    pub mean_score: Option<f64>,
+    #[serde(default)]
+    pub low_score: Option<f64>,
+    #[serde(default)]
+    pub mean_grade: QualityGrade,
+    #[serde(default)]
+    pub low_grade: QualityGrade,
+}
+
+/// Confidence report returned alongside the document
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ConfidenceReport {
+    /// scores per page number
+    #[serde(default)]
+    pub pages: BTreeMap<usize, PageConfidence>,
+    #[serde(flatten)]
+    pub overall: PageConfidence,
+}
+
+impl ConfidenceReport {
+    /// recomputes the overall mean and low scores from the pages
+    pub(crate) fn summarize(&mut self) {
+        let means: Vec<f64> = self.pages.values().filter_map(|p| p.mean_score).collect();
+        let lows = self
+            .pages
+            .values()
+            .filter_map(|p| p.low_score.or(p.mean_score));
+
+        self.overall.mean_score =
+            (!means.is_empty()).then(|| means.iter().sum::<f64>() / means.len() as f64);
+        self.overall.low_score = lows.reduce(f64::min);
+        self.overall.mean_grade = self
+            .pages
+            .values()
+            .map(|p| p.mean_grade)
+            .filter(|g| *g != QualityGrade::Unspecified)
+            .min()
+            .unwrap_or_default();
+        self.overall.low_grade = self
+            .pages
+            .values()
+            .map(|p| p.low_grade)
+            .filter(|g| *g != QualityGrade::Unspecified)
+            .min()
+            .unwrap_or_default();
+    }
+}
+
+impl OcrText {
+    /// true if the confidence of the text is known and below the threshold
+    pub fn is_low_confidence(&self, threshold: f64) -> bool {
+        self.confidence.is_some_and(|c| c < threshold)
+    }
+}
+
+impl ParsedDoc {
+    /// indexes of the texts whose confidence is below the threshold
+    pub fn low_confidence(&self, threshold: f64) -> Vec<usize> {
+        self.texts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, t)| t.is_low_confidence(threshold).then_some(i))
+            .collect()
+    }
+
+    /// a copy of the document without the texts below the threshold,
+    /// texts without a confidence are kept
+    pub fn filter_confidence(&self, threshold: f64) -> ParsedDoc {
+        let mut doc = self.clone();
+        doc.texts.retain(|t| !t.is_low_confidence(threshold));
+        doc
+    }
+
+    /// confidence of a page, if the server reported it
+    pub fn page_confidence(&self, page_no: usize) -> Option<&PageConfidence> {
+        self.confidence.as_ref()?.pages.get(&page_no)
+    }
+
+    /// Overall quality of the document between 0.0 and 1.0.
+    /// uses the mean score reported by the server, or the average
+    /// confidence of the texts weighted by their length.
+    /// missing pages count as 0.0. `None` if nothing is known
+    pub fn quality_score(&self) -> Option<f64> {
+        let reported = self.confidence.as_ref().and_then(|c| c.overall.mean_score);
+
+        let score = reported.or_else(|| {
+            let (sum, weight) = self
+                .texts
+                .iter()
+                .filter_map(|t| {
+                    t.confidence
+                        .map(|c| (c, t.text.chars().count().max(1) as f64))
+                })
+                .fold((0.0, 0.0), |(sum, weight), (c, w)| {
+                    (sum + c * w, weight + w)
+                });
+
+            (weight > 0.0).then(|| sum / weight)
+        })?;
+
+        let pages = self.page_count().max(1) as f64;
+        let missing = self.missing_pages.len() as f64;
+        Some(score * (pages - missing) / pages)
+    }
+
+    /// true if the document should be looked at by a person,
+    /// documents without any confidence information are flagged as well
+    pub fn needs_review(&self, threshold: f64) -> bool {
+        self.quality_score().is_none_or(|s| s < threshold)
+    }
+}
+
+#[test]
+fn quality() {
+    let doc: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [
+            { "text": "TOTAL", "confidence": 0.98, "prov": [] },
+            { "text": "$28,4?6.68", "confidence": 0.41, "prov": [] },
+            { "text": "Golden Waffles", "prov": [] },
+        ],
+    }))
+    .unwrap();
+
+    assert_eq!(doc.low_confidence(0.5), vec![1]);
+    assert_eq!(doc.filter_confidence(0.5).texts.len(), 2);
+    let score = doc.quality_score().unwrap();
+    assert!((score - (0.98 * 5.0 + 0.41 * 10.0) / 15.0).abs() < 1e-9);
+    assert!(doc.needs_review(0.8));
+
+    let doc: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [],
+        "confidence": {
+            "pages": { "1": { "ocr_score": 0.9, "mean_score": 0.9, "mean_grade": "excellent" } },
+            "mean_score": 0.9,
+            "low_score": null,
+            "mean_grade": "excellent"
+        }
+    }))
+    .unwrap();
+
+    assert_eq!(doc.page_confidence(1).unwrap().ocr_score, Some(0.9));
+    assert_eq!(doc.quality_score(), Some(0.9));
+    assert!(!doc.needs_review(0.8));
+}
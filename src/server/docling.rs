@@ -1,12 +1,17 @@
-use std::io::Cursor;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{Cursor, Read},
+    path::Path,
+};
 
 use image::DynamicImage;
-use regex::RegexBuilder;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::err::OcrResult;
+use super::confidence::ConfidenceReport;
+use crate::{OcrErrs, err::OcrResult, lang::Language, pdf::doc::PdfDoc};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct BoundingBox {
     pub t: f64,
     pub l: f64,
@@ -15,22 +20,283 @@ pub struct BoundingBox {
     pub coord_origin: String,
 }
 
-#[derive(Debug, Deserialize)]
+impl BoundingBox {
+    /// Docling uses `BOTTOMLEFT` for PDF backed pages
+    /// and `TOPLEFT` for images
+    pub fn is_bottom_left(&self) -> bool {
+        self.coord_origin.eq_ignore_ascii_case("BOTTOMLEFT")
+    }
+
+    /// top edge on an axis that grows downwards regardless of the origin.
+    /// NOTE: for bottom left boxes the value is negated so it is only
+    /// meaningful when compared with other boxes of the same page
+    pub fn top(&self) -> f64 {
+        if self.is_bottom_left() {
+            -self.t
+        } else {
+            self.t
+        }
+    }
+
+    /// bottom edge on an axis that grows downwards, see `Self::top`
+    pub fn bottom(&self) -> f64 {
+        if self.is_bottom_left() {
+            -self.b
+        } else {
+            self.b
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        (self.r - self.l).abs()
+    }
+
+    pub fn height(&self) -> f64 {
+        (self.bottom() - self.top()).abs()
+    }
+
+    pub fn center_y(&self) -> f64 {
+        (self.top() + self.bottom()) / 2.0
+    }
+
+    /// length of the vertical overlap between both boxes
+    pub fn v_overlap(&self, other: &Self) -> f64 {
+        (self.bottom().min(other.bottom()) - self.top().max(other.top())).max(0.0)
+    }
+
+    /// length of the horizontal overlap between both boxes
+    pub fn h_overlap(&self, other: &Self) -> f64 {
+        (self.r.min(other.r) - self.l.max(other.l)).max(0.0)
+    }
+
+    /// the same box with its origin at the top left of the page
+    pub fn to_top_left(&self, page_height: f64) -> Self {
+        if !self.is_bottom_left() {
+            return self.clone();
+        }
+
+        Self {
+            t: page_height - self.t,
+            l: self.l,
+            r: self.r,
+            b: page_height - self.b,
+            coord_origin: "TOPLEFT".into(),
+        }
+    }
+
+    /// smallest box containing both boxes.
+    /// the origin of `self` is kept, both boxes should share the same origin
+    pub fn union(&self, other: &Self) -> Self {
+        let (t, b) = if self.is_bottom_left() {
+            (self.t.max(other.t), self.b.min(other.b))
+        } else {
+            (self.t.min(other.t), self.b.max(other.b))
+        };
+
+        Self {
+            t,
+            l: self.l.min(other.l),
+            r: self.r.max(other.r),
+            b,
+            coord_origin: self.coord_origin.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Prov {
     pub page_no: usize,
     pub bbox: BoundingBox,
     pub charspan: [usize; 2],
+    /// index of the image (`PdfDoc::imgs`) the text was found in,
+    /// only set on merged documents
+    #[serde(default)]
+    pub source_img: Option<usize>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct OcrText {
     pub prov: Vec<Prov>,
     pub text: String,
+    /// element type assigned by Docling i.e. `text`, `section_header`, `list_item`
+    #[serde(default)]
+    pub label: Option<String>,
+    /// confidence of the recognition between 0.0 and 1.0, if reported
+    #[serde(default)]
+    pub confidence: Option<f64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ParsedDoc {
     pub texts: Vec<OcrText>,
+    /// key value regions detected by Docling, usually empty
+    #[serde(default)]
+    pub key_value_items: Vec<KeyValueItem>,
+    #[serde(default)]
+    pub tables: Vec<TableItem>,
+    /// pages that could not be processed, only set on merged documents
+    #[serde(default)]
+    pub missing_pages: Vec<MissingPage>,
+    /// confidence of the conversion per page, if reported
+    #[serde(default)]
+    pub confidence: Option<ConfidenceReport>,
+    /// size of every page, keyed by page number
+    #[serde(default)]
+    pub pages: BTreeMap<usize, PageItem>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct PageSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PageItem {
+    pub page_no: usize,
+    pub size: PageSize,
+}
+
+/// A page of a merged document whose OCR failed
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MissingPage {
+    pub page_no: usize,
+    /// index of the image (`PdfDoc::imgs`) that failed
+    pub source_img: usize,
+    /// the error that occurred while processing the image
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphCellLabel {
+    Unspecified,
+    Key,
+    Value,
+    Checkbox,
+    /// labels added by newer Docling versions
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GraphCell {
+    pub label: GraphCellLabel,
+    pub cell_id: usize,
+    pub text: String,
+    pub prov: Option<Prov>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphLinkLabel {
+    Unspecified,
+    ToValue,
+    ToKey,
+    ToParent,
+    ToChild,
+    /// labels added by newer Docling versions
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GraphLink {
+    pub label: GraphLinkLabel,
+    pub source_cell_id: usize,
+    pub target_cell_id: usize,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GraphData {
+    #[serde(default)]
+    pub cells: Vec<GraphCell>,
+    #[serde(default)]
+    pub links: Vec<GraphLink>,
+}
+
+/// A key value region as reported by Docling
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct KeyValueItem {
+    #[serde(default)]
+    pub prov: Vec<Prov>,
+    #[serde(default)]
+    pub graph: GraphData,
+}
+
+/// A cell of a table, offsets are 0 based and the ends are exclusive
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TableCell {
+    pub text: String,
+    pub start_row_offset_idx: usize,
+    pub end_row_offset_idx: usize,
+    pub start_col_offset_idx: usize,
+    pub end_col_offset_idx: usize,
+    #[serde(default)]
+    pub column_header: bool,
+    #[serde(default)]
+    pub row_header: bool,
+    #[serde(default)]
+    pub bbox: Option<BoundingBox>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TableData {
+    #[serde(default)]
+    pub table_cells: Vec<TableCell>,
+    #[serde(default)]
+    pub num_rows: usize,
+    #[serde(default)]
+    pub num_cols: usize,
+}
+
+/// A table as reported by Docling
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TableItem {
+    #[serde(default)]
+    pub prov: Vec<Prov>,
+    #[serde(default)]
+    pub data: TableData,
+}
+
+impl TableItem {
+    /// the text of every cell by row and column,
+    /// cells spanning several rows or columns are repeated
+    pub fn grid(&self) -> Vec<Vec<String>> {
+        let rows = self
+            .data
+            .table_cells
+            .iter()
+            .map(|c| c.end_row_offset_idx)
+            .fold(self.data.num_rows, usize::max);
+        let cols = self
+            .data
+            .table_cells
+            .iter()
+            .map(|c| c.end_col_offset_idx)
+            .fold(self.data.num_cols, usize::max);
+
+        let mut grid = vec![vec![String::new(); cols]; rows];
+        for cell in &self.data.table_cells {
+            for row in &mut grid[cell.start_row_offset_idx..cell.end_row_offset_idx] {
+                for col in &mut row[cell.start_col_offset_idx..cell.end_col_offset_idx] {
+                    col.clone_from(&cell.text);
+                }
+            }
+        }
+        grid
+    }
+
+    /// number of leading rows made of column headers
+    pub fn header_rows(&self) -> usize {
+        self.data
+            .table_cells
+            .iter()
+            .filter(|c| c.column_header)
+            .map(|c| c.end_row_offset_idx)
+            .max()
+            .unwrap_or(0)
+    }
 }
 
 impl ParsedDoc {
@@ -38,11 +304,106 @@ impl ParsedDoc {
     //     // super::OcrClient::
     // }
 
+    /// Combines the results of several images into one document.
+    /// Pages are renumbered so they follow each other and every
+    /// `Prov` records the index of the image it came from.
+    /// Failed results are kept as `MissingPage`s, one page each
+    pub fn merge<'a, I>(docs: I) -> Self
+    where
+        I: IntoIterator<Item = &'a OcrResult<ParsedDoc>>,
+    {
+        let mut merged = Self::default();
+        let mut offset = 0;
+
+        for (source_img, doc) in docs.into_iter().enumerate() {
+            let doc = match doc {
+                Ok(doc) => doc,
+                Err(err) => {
+                    offset += 1;
+                    merged.missing_pages.push(MissingPage {
+                        page_no: offset,
+                        source_img,
+                        error: err.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let shift = |prov: &mut Prov| {
+                prov.page_no += offset;
+                prov.source_img = Some(source_img);
+            };
+
+            for text in &doc.texts {
+                let mut text = text.clone();
+                text.prov.iter_mut().for_each(shift);
+                merged.texts.push(text);
+            }
+
+            for item in &doc.key_value_items {
+                let mut item = item.clone();
+                item.prov.iter_mut().for_each(shift);
+                item.graph
+                    .cells
+                    .iter_mut()
+                    .filter_map(|c| c.prov.as_mut())
+                    .for_each(shift);
+                merged.key_value_items.push(item);
+            }
+
+            for table in &doc.tables {
+                let mut table = table.clone();
+                table.prov.iter_mut().for_each(shift);
+                merged.tables.push(table);
+            }
+
+            for (page_no, page) in &doc.pages {
+                merged.pages.insert(
+                    page_no + offset,
+                    PageItem {
+                        page_no: page.page_no + offset,
+                        size: page.size,
+                    },
+                );
+            }
+
+            if let Some(report) = &doc.confidence {
+                let merged_report = merged.confidence.get_or_insert_with(Default::default);
+                for (page_no, page) in &report.pages {
+                    merged_report.pages.insert(page_no + offset, page.clone());
+                }
+            }
+
+            for missing in &doc.missing_pages {
+                merged.missing_pages.push(MissingPage {
+                    page_no: missing.page_no + offset,
+                    ..missing.clone()
+                });
+            }
+
+            offset += doc.page_count().max(1);
+        }
+
+        if let Some(report) = &mut merged.confidence {
+            report.summarize();
+        }
+
+        merged
+    }
+
+    /// number of pages, based on the highest page number found
+    pub fn page_count(&self) -> usize {
+        let texts = self.texts.iter().flat_map(|t| &t.prov).map(|p| p.page_no);
+        let missing = self.missing_pages.iter().map(|m| m.page_no);
+        let pages = self.pages.keys().copied();
+        texts.chain(missing).chain(pages).max().unwrap_or(0)
+    }
+
     /// performs a search for text in the parsed image
     /// text.
     /// NOTE: the search is case sensitive
     pub fn contains(&self, needle: &str) -> bool {
-        for OcrText { prov: _, text } in &self.texts {
+        for OcrText { text, .. } in &self.texts {
             if text.contains(needle) {
                 return true;
             }
@@ -52,14 +413,13 @@ impl ParsedDoc {
     }
 
     /// perform a case insenitive search
+    /// the needle is treated as a literal,
+    /// use `Self::contains_regex` or `Self::search` for patterns
     pub fn contains_insensitive(&self, needle: &str) -> bool {
-        let re = RegexBuilder::new(needle)
-            .case_insensitive(true)
-            .build()
-            .unwrap();
+        let needle = needle.to_lowercase();
 
-        for OcrText { prov: _, text } in &self.texts {
-            if re.is_match(text) {
+        for OcrText { text, .. } in &self.texts {
+            if text.to_lowercase().contains(&needle) {
                 return true;
             }
         }
@@ -75,45 +435,271 @@ impl ParsedDoc {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum DocType {
     Word,
+    /// legacy Word 97-2003 document
+    Doc,
     Pdf,
     /// Images will be first converted to
     /// Png
     Img,
+    Xlsx,
+    Pptx,
+    Html,
+    Markdown,
+    Csv,
+    Bmp,
+    WebP,
+    Gif,
+}
+
+impl DocType {
+    /// Detects the type from the content of the file,
+    /// falls back to the extension of the name when the content
+    /// is not conclusive (text formats, legacy office files)
+    pub fn detect(name: &str, bytes: &[u8]) -> Option<Self> {
+        Self::from_magic(bytes).or_else(|| Self::from_extension(name))
+    }
+
+    /// Detects the type from the leading bytes of the file
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        let ty = if bytes.starts_with(b"%PDF-") {
+            DocType::Pdf
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n")
+            || bytes.starts_with(&[0xFF, 0xD8, 0xFF])
+            || bytes.starts_with(b"II*\0")
+            || bytes.starts_with(b"MM\0*")
+        {
+            DocType::Img
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            DocType::Gif
+        } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+            DocType::WebP
+        } else if is_bmp(bytes) {
+            DocType::Bmp
+        } else if bytes.starts_with(b"PK\x03\x04") {
+            // office open xml files are zip archives, the name of the
+            // first folder tells which application they belong to
+            let has = |needle: &[u8]| bytes.windows(needle.len()).any(|w| w == needle);
+            if has(b"word/") {
+                DocType::Word
+            } else if has(b"xl/") {
+                DocType::Xlsx
+            } else if has(b"ppt/") {
+                DocType::Pptx
+            } else {
+                return None;
+            }
+        } else {
+            let start = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+            let start: Vec<u8> = start
+                .iter()
+                .skip_while(|b| b.is_ascii_whitespace())
+                .take(14)
+                .map(u8::to_ascii_lowercase)
+                .collect();
+
+            if start.starts_with(b"<!doctype html") || start.starts_with(b"<html") {
+                DocType::Html
+            } else {
+                return None;
+            }
+        };
+
+        Some(ty)
+    }
+
+    /// Detects the type from the extension of the file name, case insensitive
+    pub fn from_extension(name: &str) -> Option<Self> {
+        let ext = std::path::Path::new(name)
+            .extension()?
+            .to_str()?
+            .to_ascii_lowercase();
+
+        let ty = match ext.as_str() {
+            "docx" => DocType::Word,
+            "doc" => DocType::Doc,
+            "pdf" => DocType::Pdf,
+            "jpg" | "jpeg" | "tif" | "tiff" | "png" => DocType::Img,
+            "xlsx" => DocType::Xlsx,
+            "pptx" => DocType::Pptx,
+            "html" | "htm" | "xhtml" => DocType::Html,
+            "md" | "markdown" => DocType::Markdown,
+            "csv" => DocType::Csv,
+            "bmp" => DocType::Bmp,
+            "webp" => DocType::WebP,
+            "gif" => DocType::Gif,
+            _ => return None,
+        };
+
+        Some(ty)
+    }
+
+    /// mime type sent alongside the file.
+    /// `Img` covers several formats, see `OcrDoc::mime`
+    pub fn mime(&self) -> &'static str {
+        match self {
+            DocType::Word => {
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+            }
+            DocType::Doc => "application/msword",
+            DocType::Pdf => "application/pdf",
+            DocType::Img => "image/png",
+            DocType::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            DocType::Pptx => {
+                "application/vnd.openxmlformats-officedocument.presentationml.presentation"
+            }
+            DocType::Html => "text/html",
+            DocType::Markdown => "text/markdown",
+            DocType::Csv => "text/csv",
+            DocType::Bmp => "image/bmp",
+            DocType::WebP => "image/webp",
+            DocType::Gif => "image/gif",
+        }
+    }
+
+    /// extension matching the type.
+    /// `Img` covers several formats, see `OcrDoc::new`
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DocType::Word => "docx",
+            DocType::Doc => "doc",
+            DocType::Pdf => "pdf",
+            DocType::Img => "png",
+            DocType::Xlsx => "xlsx",
+            DocType::Pptx => "pptx",
+            DocType::Html => "html",
+            DocType::Markdown => "md",
+            DocType::Csv => "csv",
+            DocType::Bmp => "bmp",
+            DocType::WebP => "webp",
+            DocType::Gif => "gif",
+        }
+    }
+}
+
+/// "BM" followed by the size of the file and two reserved zeros,
+/// text files such as "BMW,..." do not pass
+fn is_bmp(bytes: &[u8]) -> bool {
+    if bytes.len() < 14 || !bytes.starts_with(b"BM") {
+        return false;
+    }
+
+    let size = u32::from_le_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]);
+    size as usize == bytes.len() && bytes[6..10] == [0; 4]
+}
+
+/// makes the extension of the name match the detected type
+/// so "scan" becomes "scan.pdf" and a jpeg named "photo.pdf" becomes "photo.jpg"
+fn fix_extension(name: String, ty: DocType, bytes: &[u8]) -> String {
+    let path = Path::new(&name);
+    let ext = path.extension().and_then(|e| e.to_str());
+
+    let matches = match ty {
+        DocType::Img => {
+            let ext_format = ext.and_then(image::ImageFormat::from_extension);
+            ext_format.is_some() && ext_format == image::guess_format(bytes).ok()
+        }
+        ty => DocType::from_extension(&name) == Some(ty),
+    };
+    if matches {
+        return name;
+    }
+
+    let new_ext = match ty {
+        DocType::Img => image::guess_format(bytes)
+            .ok()
+            .and_then(|f| f.extensions_str().first().copied())
+            .unwrap_or("png"),
+        ty => ty.extension(),
+    };
+
+    // replace an extension we know of, otherwise it is part of the name
+    if ext.is_some_and(|e| DocType::from_extension(&format!("_.{e}")).is_some()) {
+        path.with_extension(new_ext).to_string_lossy().into_owned()
+    } else {
+        format!("{name}.{new_ext}")
+    }
 }
 
 /// Represents the OCR Document
 /// This is the document that will be sent to the server
 #[derive(Debug, Clone)]
-pub struct OcrDoc<'a> {
+pub struct OcrDoc {
     /// Type of the document
     pub ty: DocType,
-    /// name of the file
-    pub name: &'a str,
+    /// name of the file, forwarded to the server
+    pub name: String,
     /// bytes
     pub bytes: Vec<u8>,
+    /// languages of the document, the server assumes english if empty
+    pub lang: Vec<Language>,
 }
 
-impl<'a> OcrDoc<'a> {
-    pub fn new(name: &'a str, bytes: Vec<u8>) -> Option<Self> {
-        let ty = if name.ends_with(".doc") || name.ends_with(".docx") {
-            DocType::Word
-        } else if name.ends_with(".pdf") {
-            DocType::Pdf
-        } else if name.ends_with(".jpg")
-            || name.ends_with(".jpeg")
-            || name.ends_with(".tif")
-            || name.ends_with(".tiff")
-            || name.ends_with(".png")
-        {
-            DocType::Img
-        } else {
-            return None;
-        };
+impl OcrDoc {
+    /// the type is detected from the content of the file
+    /// and then from the extension of the name.
+    /// the extension of the name is corrected to match the type.
+    /// returns `None` if the type is not supported
+    pub fn new<S: Into<String>>(name: S, bytes: Vec<u8>) -> Option<Self> {
+        let name = name.into();
+        let ty = DocType::detect(&name, &bytes)?;
+        Some(Self {
+            ty,
+            name: fix_extension(name, ty, &bytes),
+            bytes,
+            lang: Vec::new(),
+        })
+    }
+
+    /// reads the file at the given path, the file name is kept
+    pub fn from_path<P: AsRef<Path>>(path: P) -> OcrResult<Self> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "[Unknown]".into());
+
+        Self::from_reader(File::open(path)?, name)
+    }
+
+    /// reads the whole reader into memory
+    pub fn from_reader<R: Read, S: Into<String>>(mut reader: R, name: S) -> OcrResult<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        let name = name.into();
+        match DocType::detect(&name, &bytes) {
+            Some(ty) => Ok(Self {
+                ty,
+                name: fix_extension(name, ty, &bytes),
+                bytes,
+                lang: Vec::new(),
+            }),
+            None => Err(OcrErrs::UnsupportedDoc(name)),
+        }
+    }
 
-        Some(Self { ty, name, bytes })
+    /// sets the languages of the document
+    pub fn with_lang<I: IntoIterator<Item = Language>>(mut self, langs: I) -> Self {
+        self.lang = langs.into_iter().collect();
+        self
+    }
+
+    /// mime type of the document
+    pub fn mime(&self) -> &'static str {
+        match self.ty {
+            DocType::Img => image::guess_format(&self.bytes)
+                .map(|f| f.to_mime_type())
+                .unwrap_or("image/png"),
+            ty => ty.mime(),
+        }
     }
 
     pub fn from_img(img: &DynamicImage) -> OcrResult<Self> {
+        Self::from_img_named(img, "[Unknown].png")
+    }
+
+    /// encodes the image as png, the name should end with `.png`
+    pub fn from_img_named<S: Into<String>>(img: &DynamicImage, name: S) -> OcrResult<Self> {
         // Convert DynamicImage to bytes
         let mut img_bytes = Vec::new();
 
@@ -121,14 +707,46 @@ impl<'a> OcrDoc<'a> {
 
         Ok(Self {
             ty: DocType::Img,
-            name: "[Unknown].png",
+            name: name.into(),
             bytes: img_bytes,
+            lang: Vec::new(),
         })
     }
+
+    /// the whole pdf, named after the original file
+    pub fn from_pdf(pdf: &PdfDoc) -> Self {
+        Self {
+            ty: DocType::Pdf,
+            name: fix_extension(pdf.file_name().to_owned(), DocType::Pdf, &pdf.bytes),
+            bytes: pdf.bytes.clone(),
+            lang: pdf.lang.clone(),
+        }
+    }
+
+    /// renders a page of the pdf (0 based index).
+    /// named after the pdf i.e. `invoice.pdf#page-1.png`
+    pub fn from_pdf_page(pdf: &PdfDoc, page_idx: u16) -> OcrResult<Self> {
+        let img = pdf.render_page(page_idx)?;
+        let name = format!("{}#page-{}.png", pdf.file_name(), page_idx + 1);
+        Ok(Self::from_img_named(&img, name)?.with_lang(pdf.lang.iter().copied()))
+    }
+
+    /// an embedded image of the pdf (index into `PdfDoc::imgs`).
+    /// named after the pdf i.e. `invoice.pdf#img-1.png`
+    pub fn from_pdf_img(pdf: &PdfDoc, img_idx: usize) -> OcrResult<Self> {
+        let img = pdf
+            .imgs
+            .get(img_idx)
+            .ok_or(OcrErrs::MissingImage(img_idx))?;
+        let name = format!("{}#img-{}.png", pdf.file_name(), img_idx + 1);
+        Ok(Self::from_img_named(img, name)?.with_lang(pdf.lang.iter().copied()))
+    }
 }
 
 #[test]
 fn a() {
+    use regex::RegexBuilder;
+
     let p = "Some random Ass text goes hehre";
 
     dbg!(p.contains("ass"));
@@ -140,3 +758,92 @@ fn a() {
 
     dbg!(re.is_match(p));
 }
+
+#[test]
+fn merge() {
+    let page = |text: &str, page_no: usize| -> OcrResult<ParsedDoc> {
+        Ok(serde_json::from_value(serde_json::json!({
+            "texts": [{ "text": text, "prov": [{ "page_no": page_no, "charspan": [0, text.len()], "bbox": { "l": 0.0, "t": 0.0, "r": 10.0, "b": 10.0, "coord_origin": "TOPLEFT" } }] }]
+        }))
+        .unwrap())
+    };
+
+    let docs = vec![
+        page("first", 1),
+        Err(crate::OcrErrs::IO(std::io::ErrorKind::TimedOut.into())),
+        page("third", 1),
+        page("fifth", 2),
+    ];
+    let merged = ParsedDoc::merge(&docs);
+
+    let pages: Vec<_> = merged
+        .texts
+        .iter()
+        .map(|t| (t.prov[0].page_no, t.prov[0].source_img))
+        .collect();
+    assert_eq!(pages, vec![(1, Some(0)), (3, Some(2)), (5, Some(3))]);
+    assert_eq!(merged.missing_pages[0].page_no, 2);
+    assert_eq!(merged.missing_pages[0].source_img, 1);
+    assert_eq!(merged.page_count(), 5);
+}
+
+#[test]
+fn sniffing() {
+    assert_eq!(DocType::detect("scan", b"%PDF-1.7\n"), Some(DocType::Pdf));
+    assert_eq!(DocType::detect("invoice.PDF", b""), Some(DocType::Pdf));
+    // content wins over a wrong extension
+    assert_eq!(
+        DocType::detect("photo.pdf", b"\xFF\xD8\xFF\xE0"),
+        Some(DocType::Img)
+    );
+    assert_eq!(
+        DocType::detect("upload", b"RIFF\0\0\0\0WEBPVP8 "),
+        Some(DocType::WebP)
+    );
+    assert_eq!(
+        DocType::detect("upload", b"PK\x03\x04....xl/workbook.xml"),
+        Some(DocType::Xlsx)
+    );
+    assert_eq!(
+        DocType::detect("page", b"\n  <!DOCTYPE html><html>"),
+        Some(DocType::Html)
+    );
+    assert_eq!(
+        DocType::detect("notes.Md", b"# Notes"),
+        Some(DocType::Markdown)
+    );
+    assert_eq!(DocType::detect("notes.txt", b"plain text"), None);
+    assert_eq!(
+        DocType::detect("cars.csv", b"BMW,X5,2021,54000"),
+        Some(DocType::Csv)
+    );
+    assert_eq!(DocType::from_magic(b"BMW,X5,2021,54000"), None);
+    let mut bmp = b"BM\0\0\0\0\0\0\0\0\x1a\0\0\0".to_vec();
+    bmp[2] = bmp.len() as u8;
+    assert_eq!(DocType::from_magic(&bmp), Some(DocType::Bmp));
+    assert_eq!(DocType::detect("memo.DOC", b""), Some(DocType::Doc));
+    assert_eq!(DocType::Doc.mime(), "application/msword");
+}
+
+#[test]
+fn owned_doc() {
+    let doc = OcrDoc::from_reader(&b"%PDF-1.4\n"[..], "scan").unwrap();
+    assert_eq!((doc.ty, doc.name.as_str()), (DocType::Pdf, "scan.pdf"));
+
+    // the name sent to the server follows the content
+    let doc = OcrDoc::new("photo.pdf", b"\xFF\xD8\xFF\xE0".to_vec()).unwrap();
+    assert_eq!((doc.ty, doc.name.as_str()), (DocType::Img, "photo.jpg"));
+    let doc = OcrDoc::new("photo.JPEG", b"\xFF\xD8\xFF\xE0".to_vec()).unwrap();
+    assert_eq!(doc.name, "photo.JPEG");
+    let doc = OcrDoc::new("inv.2024", b"%PDF-1.4\n".to_vec()).unwrap();
+    assert_eq!(doc.name, "inv.2024.pdf");
+
+    let doc = OcrDoc::from_path("./tests/1.jpg").unwrap();
+    assert_eq!((doc.ty, doc.mime()), (DocType::Img, "image/jpeg"));
+    assert_eq!(doc.name, "1.jpg");
+
+    assert!(matches!(
+        OcrDoc::from_reader(&b"hello"[..], "notes.txt"),
+        Err(OcrErrs::UnsupportedDoc(_))
+    ));
+}
@@ -0,0 +1,106 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::money::Money;
+
+/// A postal address as printed on the invoice
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Address {
+    /// the address as returned by the server
+    pub text: String,
+    /// name and street lines, without the city line
+    pub lines: Vec<String>,
+    pub city: Option<String>,
+    /// state or province code
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+}
+
+/// `Springfield, IL 62704` and `Toronto ON M5V 2T6`
+static CITY_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.+?),?\s+([A-Z]{2})\.?,?\s+(\d{5}(?:-\d{4})?|[A-Z]\d[A-Z]\s?\d[A-Z]\d)$")
+        .unwrap()
+});
+
+impl Address {
+    /// Reads an address written on several lines or on a single
+    /// line separated by commas. `None` if the text is empty
+    pub fn parse(text: &str) -> Option<Address> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+
+        let mut lines: Vec<String> = match text.contains('\n') {
+            true => text.lines().map(str::trim).map(String::from).collect(),
+            false => text.split(',').map(str::trim).map(String::from).collect(),
+        };
+        lines.retain(|l| !l.is_empty());
+
+        let mut address = Address {
+            text: text.to_owned(),
+            ..Default::default()
+        };
+
+        // the city may have been split from its state by a comma
+        for n in [1, 2] {
+            let Some(start) = lines.len().checked_sub(n) else {
+                break;
+            };
+            let city_line = lines[start..].join(", ");
+            if let Some(caps) = CITY_LINE.captures(&city_line) {
+                address.city = Some(caps[1].trim_end_matches(',').to_owned());
+                address.state = Some(caps[2].to_owned());
+                address.postal_code = Some(caps[3].to_owned());
+                lines.truncate(start);
+                break;
+            }
+        }
+
+        address.lines = lines;
+        Some(address)
+    }
+}
+
+/// A tax by its type, i.e. `GST`, `PST` or `Sales Tax`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TaxAmount {
+    pub kind: String,
+    pub amount: Money,
+}
+
+/// Keeps the digits and letters of a tax id without its label,
+/// `EIN: 12-3456789` is `123456789`
+pub fn normalize_tax_id(id: &str) -> Option<String> {
+    let id = id.rsplit(':').next().unwrap_or(id);
+    let id: String = id
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    (!id.is_empty()).then_some(id)
+}
+
+#[test]
+fn address() {
+    let address =
+        Address::parse("Golden Waffles LLC\n123 Main St, Suite 4\nSpringfield, IL 62704").unwrap();
+    assert_eq!(
+        address.lines,
+        ["Golden Waffles LLC", "123 Main St, Suite 4"]
+    );
+    assert_eq!(address.city.as_deref(), Some("Springfield"));
+    assert_eq!(address.state.as_deref(), Some("IL"));
+    assert_eq!(address.postal_code.as_deref(), Some("62704"));
+
+    let address = Address::parse("PO Box 9, Toronto, ON M5V 2T6").unwrap();
+    assert_eq!(address.lines, ["PO Box 9"]);
+    assert_eq!(address.city.as_deref(), Some("Toronto"));
+
+    assert_eq!(
+        normalize_tax_id("EIN: 12-3456789").as_deref(),
+        Some("123456789")
+    );
+}
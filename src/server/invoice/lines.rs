@@ -0,0 +1,303 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    money::{Decimal, Money, MoneyFormat, parse_number},
+    server::docling::{ParsedDoc, TableItem},
+};
+
+/// A single line of an invoice
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct LineItem {
+    pub item_code: Option<String>,
+    pub description: String,
+    pub quantity: Option<Decimal>,
+    /// unit of measure i.e. `CS`, `EA`, `LB`
+    pub unit: Option<String>,
+    pub unit_price: Option<Money>,
+    /// extended amount of the line
+    pub amount: Option<Money>,
+}
+
+impl LineItem {
+    /// quantity times unit price, if both are known
+    pub fn computed_amount(&self) -> Option<Money> {
+        let price = self.unit_price?;
+        Some(Money::new(self.quantity? * price.amount, price.currency))
+    }
+
+    /// the amount of the line, computed if it was not printed
+    pub fn total(&self) -> Option<Money> {
+        self.amount.or_else(|| self.computed_amount())
+    }
+
+    /// reads the line item returned by the server,
+    /// amounts without a currency are in the currency of the format
+    pub fn from_response(value: LineItemResponse, format: &MoneyFormat) -> Self {
+        let money = |s: Option<String>| s.and_then(|s| Money::parse_with(&s, format));
+        let text = |s: Option<String>| s.map(|s| s.trim().to_owned()).filter(|s| !s.is_empty());
+
+        Self {
+            item_code: text(value.item_code),
+            description: text(value.description).unwrap_or_default(),
+            quantity: value.quantity.and_then(|q| quantity(&q, format)),
+            unit: text(value.unit),
+            unit_price: money(value.unit_price),
+            amount: money(value.amount),
+        }
+    }
+}
+
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(-?)(\d(?:[\d.,]*\d)?)").unwrap());
+
+/// the first number of the text, `4` of `4 CS` or `2 @ $5.00`.
+/// read with the separators of the format, any currency is ignored
+fn quantity(text: &str, format: &MoneyFormat) -> Option<Decimal> {
+    let caps = NUMBER.captures(text)?;
+    let qty = parse_number(&caps[2], format.separators)?;
+    Some(if caps[1].is_empty() { qty } else { -qty })
+}
+
+/// A line item as returned by the server, every field is text
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct LineItemResponse {
+    pub item_code: Option<String>,
+    pub description: Option<String>,
+    pub quantity: Option<String>,
+    pub unit: Option<String>,
+    pub unit_price: Option<String>,
+    pub amount: Option<String>,
+}
+
+impl From<LineItemResponse> for LineItem {
+    fn from(value: LineItemResponse) -> Self {
+        LineItem::from_response(value, &MoneyFormat::default())
+    }
+}
+
+/// Columns of a line item table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Code,
+    Description,
+    Quantity,
+    Unit,
+    UnitPrice,
+    Amount,
+}
+
+/// guesses the column from its header, most specific first
+/// so `Unit Price` is not taken for a unit and `Item Description` for a code
+fn column(header: &str) -> Option<Column> {
+    let h = header.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| h.contains(w));
+
+    let col = if has(&["amount", "ext", "line total", "total"]) {
+        Column::Amount
+    } else if has(&["price", "rate", "cost", "each"]) {
+        Column::UnitPrice
+    } else if has(&["desc", "product name", "product", "name"]) {
+        Column::Description
+    } else if has(&["qty", "quantity", "shipped", "ordered", "cases"]) {
+        Column::Quantity
+    } else if has(&["unit", "uom", "u/m", "pack", "size"]) {
+        Column::Unit
+    } else if has(&["item", "code", "sku", "part", "no.", "#"]) {
+        Column::Code
+    } else {
+        return None;
+    };
+    Some(col)
+}
+
+/// rows below the lines holding the totals
+static SUMMARY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^\s*(?:sub\s*-?\s*total|total|tax|sales tax|freight|shipping|delivery|discount|balance|amount due|deposit)\b")
+        .unwrap()
+});
+
+/// Reads the line items of a table, empty if the
+/// table does not look like a line item table
+pub fn line_items_from_table(table: &TableItem, format: &MoneyFormat) -> Vec<LineItem> {
+    let grid = table.grid();
+    let header_rows = match table.header_rows() {
+        0 => 1,
+        n => n,
+    };
+    if grid.len() <= header_rows {
+        return Vec::new();
+    }
+
+    let cols = grid[0].len();
+    let columns: Vec<Option<Column>> = (0..cols)
+        .map(|c| {
+            let header: Vec<&str> = grid[..header_rows].iter().map(|r| r[c].as_str()).collect();
+            column(&header.join(" "))
+        })
+        .collect();
+
+    let has = |col| columns.contains(&Some(col));
+    let named = has(Column::Description) || has(Column::Code);
+    let priced = has(Column::Amount) || has(Column::UnitPrice);
+    if !named || !priced {
+        return Vec::new();
+    }
+
+    let mut items = Vec::new();
+    for row in &grid[header_rows..] {
+        let mut item = LineItem::default();
+        for (cell, col) in row.iter().zip(&columns) {
+            let cell = cell.trim();
+            if cell.is_empty() {
+                continue;
+            }
+            match col {
+                Some(Column::Code) => item.item_code = Some(cell.to_owned()),
+                Some(Column::Description) => {
+                    if !item.description.is_empty() {
+                        item.description.push(' ');
+                    }
+                    item.description.push_str(cell);
+                }
+                Some(Column::Quantity) => item.quantity = quantity(cell, format),
+                Some(Column::Unit) => item.unit = Some(cell.to_owned()),
+                Some(Column::UnitPrice) => item.unit_price = Money::parse_with(cell, format),
+                Some(Column::Amount) => item.amount = Money::parse_with(cell, format),
+                None => {}
+            }
+        }
+
+        let label = row.iter().find(|c| !c.trim().is_empty());
+        if label.is_some_and(|l| SUMMARY.is_match(l)) && item.quantity.is_none() {
+            continue;
+        }
+        let named = !item.description.is_empty() || item.item_code.is_some();
+        if named && (item.total().is_some() || item.unit_price.is_some()) {
+            items.push(item);
+        }
+    }
+    items
+}
+
+impl ParsedDoc {
+    /// line items found in the tables of the document
+    pub fn line_items(&self, format: &MoneyFormat) -> Vec<LineItem> {
+        self.tables
+            .iter()
+            .flat_map(|t| line_items_from_table(t, format))
+            .collect()
+    }
+}
+
+/// Sum of the line items compared with the subtotal of the invoice
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LineSumCheck {
+    pub lines_total: Money,
+    pub expected: Money,
+    /// `lines_total - expected`
+    pub difference: Money,
+}
+
+impl LineSumCheck {
+    /// `None` if the amounts are in different currencies
+    pub fn new(lines_total: Money, expected: Money) -> Option<Self> {
+        Some(Self {
+            lines_total,
+            expected,
+            difference: lines_total.checked_sub(expected)?,
+        })
+    }
+
+    /// true if the difference is within the tolerance, i.e. `0.01` for rounding
+    pub fn is_ok(&self, tolerance: Decimal) -> bool {
+        self.difference.amount.abs() <= tolerance
+    }
+}
+
+#[test]
+fn lines() {
+    let cell = |text: &str, row: usize, col: usize| {
+        serde_json::json!({
+            "text": text,
+            "start_row_offset_idx": row, "end_row_offset_idx": row + 1,
+            "start_col_offset_idx": col, "end_col_offset_idx": col + 1,
+            "column_header": row == 0,
+        })
+    };
+    let rows = [
+        [
+            "Item #",
+            "Description",
+            "Qty",
+            "U/M",
+            "Unit Price",
+            "Ext. Amount",
+        ],
+        ["10442", "Maple syrup 1gal", "4", "CS", "$32.50", "$130.00"],
+        [
+            "20910",
+            "Paper towels 12ct",
+            "2",
+            "CS",
+            "1.234,50",
+            "2.469,00",
+        ],
+        [
+            "30112",
+            "Chicken breast",
+            "23.450",
+            "LB",
+            "$2.125",
+            "$49.83",
+        ],
+        ["", "Subtotal", "", "", "", "2,648.83"],
+    ];
+    let cells: Vec<_> = rows
+        .iter()
+        .enumerate()
+        .flat_map(|(r, row)| row.iter().enumerate().map(move |(c, t)| cell(t, r, c)))
+        .collect();
+    let doc: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [],
+        "tables": [{ "prov": [], "data": { "table_cells": cells, "num_rows": 5, "num_cols": 6 } }]
+    }))
+    .unwrap();
+
+    let items = doc.line_items(&MoneyFormat::default());
+    assert_eq!(items.len(), 3);
+    assert_eq!(
+        items[0],
+        LineItem {
+            item_code: Some("10442".into()),
+            description: "Maple syrup 1gal".into(),
+            quantity: Some(4.into()),
+            unit: Some("CS".into()),
+            unit_price: Money::parse("32.50"),
+            amount: Money::parse("130.00"),
+        }
+    );
+    assert_eq!(items[1].amount, Money::parse("2469.00"));
+    // catch weight, priced per pound
+    assert_eq!(items[2].quantity, Some(Decimal::new(23450, 3)));
+    assert_eq!(items[2].unit_price, Money::parse("2.125"));
+
+    let item = LineItem::from(LineItemResponse {
+        description: Some("Eggs".into()),
+        quantity: Some("2 @ USD 5.00".into()),
+        ..Default::default()
+    });
+    assert_eq!(item.quantity, Some(2.into()));
+
+    let res: super::InvoiceResponse = serde_json::from_value(serde_json::json!({
+        "invoice_no": "1042", "vendor": null, "acct_no": null,
+        "inv_date": null, "due_date": null, "total": "$2,841.75", "subtotal": "$2,648.83"
+    }))
+    .unwrap();
+    let mut details = super::InvoiceDetails::from(res);
+    details.fill_line_items(&doc);
+    let check = details.line_sum_check().unwrap();
+    assert!(check.is_ok(Decimal::ZERO));
+    assert_eq!(check.lines_total, Money::parse("$2,648.83").unwrap());
+}
@@ -0,0 +1,336 @@
+pub mod header;
+pub mod lines;
+pub mod terms;
+pub mod validate;
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use either::Either;
+use header::{Address, TaxAmount, normalize_tax_id};
+use lines::{LineItem, LineItemResponse, LineSumCheck};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use terms::PaymentTerms;
+
+use super::docling::ParsedDoc;
+use crate::{
+    date::DateParser,
+    money::{Decimal, Money, MoneyFormat},
+};
+
+/// Provides a convience method to deserialize
+/// invoice info received from the server
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InvoiceResponse {
+    pub invoice_no: Option<String>,
+    pub vendor: Option<String>,
+    pub acct_no: Option<String>,
+    pub inv_date: Option<String>,
+    pub due_date: Option<String>,
+    pub total: Option<String>,
+    #[serde(default)]
+    pub subtotal: Option<String>,
+    #[serde(default)]
+    pub tax: Option<String>,
+    /// amount of every tax by its type, i.e. `{"GST": "$5.00"}`
+    #[serde(default)]
+    pub taxes: BTreeMap<String, String>,
+    #[serde(default, alias = "freight")]
+    pub shipping: Option<String>,
+    #[serde(default)]
+    pub discount: Option<String>,
+    #[serde(default)]
+    pub line_items: Vec<LineItemResponse>,
+    /// payment terms i.e. `2/10 Net 30`
+    #[serde(default)]
+    pub terms: Option<String>,
+    #[serde(default, alias = "po_number")]
+    pub po_no: Option<String>,
+    #[serde(default)]
+    pub bill_to: Option<String>,
+    #[serde(default)]
+    pub ship_to: Option<String>,
+    #[serde(default)]
+    pub vendor_address: Option<String>,
+    #[serde(default)]
+    pub remit_to: Option<String>,
+    #[serde(default)]
+    pub vendor_tax_id: Option<String>,
+    #[serde(default)]
+    pub payment_instructions: Option<String>,
+    /// fields this version does not know about
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InvoiceDetails {
+    pub inv_no: Option<String>,
+    pub vendor: Option<String>,
+    pub acct_no: Option<String>,
+    pub inv_date: Either<NaiveDate, String>,
+    pub due_date: Either<NaiveDate, String>,
+    pub total: Either<Money, String>,
+    /// total before tax, shipping and discounts
+    #[serde(default)]
+    pub subtotal: Option<Money>,
+    /// all taxes, the sum of `taxes` if no total was printed
+    #[serde(default)]
+    pub tax: Option<Money>,
+    #[serde(default)]
+    pub taxes: Vec<TaxAmount>,
+    /// freight and delivery charges
+    #[serde(default)]
+    pub shipping: Option<Money>,
+    /// taken off the total, positive even if printed as a negative amount
+    #[serde(default)]
+    pub discount: Option<Money>,
+    #[serde(default)]
+    pub line_items: Vec<LineItem>,
+    /// read from the terms, or from the due date if it is not a date
+    #[serde(default)]
+    pub terms: Option<PaymentTerms>,
+    /// purchase order number
+    #[serde(default)]
+    pub po_no: Option<String>,
+    #[serde(default)]
+    pub bill_to: Option<Address>,
+    #[serde(default)]
+    pub ship_to: Option<Address>,
+    #[serde(default)]
+    pub vendor_address: Option<Address>,
+    /// where the payment is sent, if not the vendor address
+    #[serde(default)]
+    pub remit_to: Option<Address>,
+    /// letters and digits only, see `header::normalize_tax_id`
+    #[serde(default)]
+    pub vendor_tax_id: Option<String>,
+    /// bank details and other instructions, as printed
+    #[serde(default)]
+    pub payment_instructions: Option<String>,
+    /// fields of the response this version does not know about
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl InvoiceDetails {
+    /// amounts without a currency are in the currency of the total
+    pub fn money_format(&self) -> MoneyFormat {
+        self.total
+            .as_ref()
+            .left()
+            .map_or_else(MoneyFormat::default, |t| MoneyFormat::new(t.currency))
+    }
+
+    /// fills the line items from the tables of the document
+    /// if the server did not return any
+    pub fn fill_line_items(&mut self, doc: &ParsedDoc) {
+        if self.line_items.is_empty() {
+            self.line_items = doc.line_items(&self.money_format());
+        }
+    }
+
+    /// Compares the sum of the line items with the subtotal, or with
+    /// the total if the invoice has no subtotal.
+    /// `None` without line items or amount to compare with,
+    /// or if the amounts are in different currencies
+    pub fn line_sum_check(&self) -> Option<LineSumCheck> {
+        if self.line_items.is_empty() {
+            return None;
+        }
+
+        let expected = self.subtotal.or(self.total.as_ref().left().copied())?;
+        let lines_total = self
+            .line_items
+            .iter()
+            .filter_map(LineItem::total)
+            .try_fold(Money::zero(expected.currency), Money::checked_add)?;
+        LineSumCheck::new(lines_total, expected)
+    }
+
+    /// last day the early payment discount of the terms can be taken
+    pub fn discount_date(&self) -> Option<NaiveDate> {
+        let inv_date = self.inv_date.as_ref().left()?;
+        self.terms.as_ref()?.discount_date(*inv_date)
+    }
+
+    pub fn discount_percent(&self) -> Option<Decimal> {
+        self.terms.as_ref()?.discount_percent()
+    }
+}
+
+impl From<InvoiceResponse> for InvoiceDetails {
+    fn from(value: InvoiceResponse) -> Self {
+        InvoiceDetails::from_response(value, &DateParser::default())
+    }
+}
+
+impl InvoiceDetails {
+    /// reads the response of the server, dates are parsed with `dates`
+    /// so they can follow the day/month order of the vendor
+    pub fn from_response(value: InvoiceResponse, dates: &DateParser) -> Self {
+        let InvoiceResponse {
+            invoice_no,
+            vendor,
+            acct_no,
+            inv_date,
+            due_date,
+            total,
+            subtotal,
+            tax,
+            taxes,
+            shipping,
+            discount,
+            line_items,
+            terms,
+            po_no,
+            bill_to,
+            ship_to,
+            vendor_address,
+            remit_to,
+            vendor_tax_id,
+            payment_instructions,
+            extra,
+        } = value;
+
+        let inv_date = inv_date
+            .map(|s| parse_date_with(s, dates))
+            .unwrap_or_else(|| Either::Right("No Date Available".into()));
+
+        let terms = terms
+            .as_deref()
+            .and_then(PaymentTerms::parse)
+            .or_else(|| due_date.as_deref().and_then(PaymentTerms::parse));
+
+        let due_date = match due_date.map(|s| parse_date_with(s, dates)) {
+            Some(Either::Left(date)) => Either::Left(date),
+            due_date => match (&inv_date, &terms) {
+                (Either::Left(inv_date), Some(terms)) => Either::Left(terms.due_date(*inv_date)),
+                _ => due_date.unwrap_or_else(|| Either::Right("No Date Available".into())),
+            },
+        };
+
+        let text = |s: Option<String>| s.map(|s| s.trim().to_owned()).filter(|s| !s.is_empty());
+
+        let total = total
+            .map(parse_money_from_str)
+            .unwrap_or_else(|| Either::Right("Unavailable".into()));
+
+        let mut details = Self {
+            inv_no: invoice_no,
+            vendor,
+            acct_no,
+            inv_date,
+            due_date,
+            total,
+            subtotal: None,
+            tax: None,
+            taxes: Vec::new(),
+            shipping: None,
+            discount: None,
+            line_items: Vec::new(),
+            terms,
+            po_no: text(po_no),
+            bill_to: bill_to.as_deref().and_then(Address::parse),
+            ship_to: ship_to.as_deref().and_then(Address::parse),
+            vendor_address: vendor_address.as_deref().and_then(Address::parse),
+            remit_to: remit_to.as_deref().and_then(Address::parse),
+            vendor_tax_id: vendor_tax_id.as_deref().and_then(normalize_tax_id),
+            payment_instructions: text(payment_instructions),
+            extra,
+        };
+
+        let format = details.money_format();
+        let money = |s: Option<String>| s.and_then(|s| Money::parse_with(&s, &format));
+        details.subtotal = money(subtotal);
+        details.taxes = taxes
+            .into_iter()
+            .filter_map(|(kind, amount)| {
+                Some(TaxAmount {
+                    kind,
+                    amount: money(Some(amount))?,
+                })
+            })
+            .collect();
+        details.tax = money(tax).or_else(|| {
+            let mut taxes = details.taxes.iter().map(|t| t.amount);
+            let first = taxes.next()?;
+            taxes.try_fold(first, Money::checked_add)
+        });
+        details.shipping = money(shipping);
+        details.discount = money(discount).map(|d| d.abs());
+        details.line_items = line_items
+            .into_iter()
+            .map(|l| LineItem::from_response(l, &format))
+            .collect();
+        details
+    }
+}
+
+/// Parses the date from a given string
+/// if unable to get date
+/// returns the string
+pub fn parse_date_from_str(s: String) -> Either<NaiveDate, String> {
+    parse_date_with(s, &DateParser::default())
+}
+
+/// Parses the date with the given parser
+/// if unable to get date
+/// returns the string
+pub fn parse_date_with(s: String, parser: &DateParser) -> Either<NaiveDate, String> {
+    match parser.parse(&s) {
+        Some(found) => Either::Left(found.date),
+        None => Either::Right(s),
+    }
+}
+
+/// Parse the total amount due
+/// if unable to get an amount
+/// returns the string
+pub fn parse_money_from_str(s: String) -> Either<Money, String> {
+    match Money::parse(&s) {
+        Some(money) => Either::Left(money),
+        None => Either::Right(s),
+    }
+}
+
+#[test]
+fn parsing() {
+    let a = parse_money_from_str("**Total Amount:** $28,496.68".into());
+    assert_eq!(
+        a.left().map(|m| m.to_string()).as_deref(),
+        Some("28496.68 USD")
+    );
+
+    let b = parse_date_from_str("**Due Date:** Not explicitly stated, but based on the payment terms \"Net 30,\" the due date is approximately 02/05/2024.".into());
+    assert_eq!(b.left(), NaiveDate::from_ymd_opt(2024, 2, 5));
+
+    let res: InvoiceResponse = serde_json::from_value(serde_json::json!({
+        "invoice_no": "1042", "vendor": "Golden Waffles", "acct_no": null,
+        "inv_date": null, "due_date": null, "total": "CA$112.00",
+        "taxes": { "GST": "5.00", "PST": "7.00" },
+        "freight": "$4.00",
+        "po_number": "PO-77",
+        "remit_to": "Golden Waffles\nPO Box 9\nToronto, ON M5V 2T6",
+        "vendor_tax_id": "BN: 12345 6789",
+        "currency_rate": 1.35,
+    }))
+    .unwrap();
+    let details = InvoiceDetails::from(res);
+    assert_eq!(
+        details.tax.map(|t| t.to_string()).as_deref(),
+        Some("12.00 CAD")
+    );
+    assert_eq!(
+        details.shipping.map(|t| t.currency),
+        Some(crate::money::Currency::CAD)
+    );
+    assert_eq!(details.po_no.as_deref(), Some("PO-77"));
+    assert_eq!(
+        details.remit_to.unwrap().postal_code.as_deref(),
+        Some("M5V 2T6")
+    );
+    assert_eq!(details.vendor_tax_id.as_deref(), Some("123456789"));
+    assert_eq!(details.extra["currency_rate"], 1.35);
+}
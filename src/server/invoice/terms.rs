@@ -0,0 +1,173 @@
+use std::{str::FromStr, sync::LazyLock};
+
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::money::Decimal;
+
+/// When an invoice has to be paid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DueRule {
+    /// `Due on receipt`
+    OnReceipt,
+    /// cash on delivery
+    Cod,
+    /// `Net 30`, days after the invoice date
+    Net { days: u32 },
+    /// `Net 15 EOM`, days after the end of the month of the invoice
+    EndOfMonth { days: u32 },
+    /// `Net 10th Prox`, that day of the month after the invoice
+    Proximo { day: u32 },
+}
+
+/// `2/10`, the percentage taken off when paid within the days
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct EarlyDiscount {
+    pub percent: Decimal,
+    pub days: u32,
+}
+
+/// Payment terms as printed on the invoice
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PaymentTerms {
+    pub due: DueRule,
+    pub discount: Option<EarlyDiscount>,
+    /// the text the terms were read from
+    pub text: String,
+}
+
+static DISCOUNT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(\d+(?:\.\d+)?)\s*%?\s*(?:/|\s)\s*(\d+)(?:\s*days?)?[\s,]*(?:net|n)\s*/?\s*(\d+)",
+    )
+    .unwrap()
+});
+
+static EOM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:net\s*(\d+)\s*(?:days?\s*)?)?\b(?:eom|e\.o\.m\b|end of (?:the )?month)")
+        .unwrap()
+});
+
+static PROX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(\d+)(?:st|nd|rd|th)?\s*(?:day\s*)?prox(?:imo)?\b").unwrap());
+
+static RECEIPT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:up)?on receipt|due immediately|payable immediately").unwrap()
+});
+
+static COD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bc\.?o\.?d\b|cash on delivery").unwrap());
+
+static NET: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\bnet\s*/?\s*(\d+)|\bn/(\d+)|(?:payable|due)\s+(?:with)?in\s+(\d+)\s*days|(\d+)\s*days\s+net")
+        .unwrap()
+});
+
+/// the first group of the captures that matched, as a number
+fn number(caps: &regex::Captures) -> Option<u32> {
+    caps.iter().skip(1).flatten().next()?.as_str().parse().ok()
+}
+
+impl PaymentTerms {
+    /// Reads the terms from a text such as `2/10 Net 30`, `Due on receipt`,
+    /// `Net 15 EOM`, `COD`, `Net 10th Prox` or `Payable in 30 days`
+    pub fn parse(text: &str) -> Option<PaymentTerms> {
+        let discount = DISCOUNT.captures(text);
+
+        let due = if let Some(caps) = EOM.captures(text) {
+            DueRule::EndOfMonth {
+                days: caps.get(1).map_or(Some(0), |d| d.as_str().parse().ok())?,
+            }
+        } else if let Some(caps) = PROX.captures(text) {
+            DueRule::Proximo {
+                day: caps[1].parse().ok()?,
+            }
+        } else if let Some(caps) = &discount {
+            DueRule::Net {
+                days: caps[3].parse().ok()?,
+            }
+        } else if RECEIPT.is_match(text) {
+            DueRule::OnReceipt
+        } else if COD.is_match(text) {
+            DueRule::Cod
+        } else {
+            DueRule::Net {
+                days: number(&NET.captures(text)?)?,
+            }
+        };
+
+        let discount = discount.and_then(|caps| {
+            Some(EarlyDiscount {
+                percent: Decimal::from_str(&caps[1]).ok()?,
+                days: caps[2].parse().ok()?,
+            })
+        });
+
+        Some(PaymentTerms {
+            due,
+            discount,
+            text: text.trim().to_owned(),
+        })
+    }
+
+    /// the date the days of the terms are counted from
+    fn base(&self, inv_date: NaiveDate) -> NaiveDate {
+        match self.due {
+            DueRule::EndOfMonth { .. } => end_of_month(inv_date),
+            _ => inv_date,
+        }
+    }
+
+    pub fn due_date(&self, inv_date: NaiveDate) -> NaiveDate {
+        let base = self.base(inv_date);
+        match self.due {
+            DueRule::OnReceipt | DueRule::Cod => inv_date,
+            DueRule::Net { days } | DueRule::EndOfMonth { days } => {
+                base + Duration::days(days.into())
+            }
+            DueRule::Proximo { day } => {
+                let next = end_of_month(inv_date) + Duration::days(1);
+                let last = end_of_month(next).day();
+                next.with_day(day.clamp(1, last)).unwrap_or(next)
+            }
+        }
+    }
+
+    /// last day the early payment discount can be taken
+    pub fn discount_date(&self, inv_date: NaiveDate) -> Option<NaiveDate> {
+        let discount = self.discount?;
+        Some(self.base(inv_date) + Duration::days(discount.days.into()))
+    }
+
+    pub fn discount_percent(&self) -> Option<Decimal> {
+        self.discount.map(|d| d.percent)
+    }
+}
+
+fn end_of_month(date: NaiveDate) -> NaiveDate {
+    let first = date.with_day(1).unwrap_or(date);
+    first + Months::new(1) - Duration::days(1)
+}
+
+#[test]
+fn terms() {
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    let inv = date(2024, 1, 20);
+    let due = |s: &str| PaymentTerms::parse(s).map(|t| t.due_date(inv));
+
+    let terms = PaymentTerms::parse("2/10 Net 30").unwrap();
+    assert_eq!(terms.due, DueRule::Net { days: 30 });
+    assert_eq!(terms.discount_percent(), Some(Decimal::from(2)));
+    assert_eq!(terms.discount_date(inv), Some(date(2024, 1, 30)));
+    assert_eq!(terms.due_date(inv), date(2024, 2, 19));
+
+    assert_eq!(due("Due on receipt"), Some(inv));
+    assert_eq!(due("COD"), Some(inv));
+    assert_eq!(due("Net 15 EOM"), Some(date(2024, 2, 15)));
+    assert_eq!(due("Net 10th Prox"), Some(date(2024, 2, 10)));
+    assert_eq!(due("Payable in 30 days"), Some(date(2024, 2, 19)));
+    assert_eq!(due("1.5% 10 Net 45").unwrap(), date(2024, 3, 5));
+    assert_eq!(due("Thank you for your business"), None);
+}
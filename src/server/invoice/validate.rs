@@ -0,0 +1,273 @@
+use chrono::{Local, NaiveDate};
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+use super::{InvoiceDetails, lines::LineSumCheck};
+use crate::{
+    err::OcrResult,
+    money::{Currency, Decimal, Money},
+};
+
+/// Something in the invoice a person should look at
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValidationWarning {
+    /// the field was not found or could not be read
+    Missing {
+        field: &'static str,
+    },
+    /// subtotal + tax + shipping - discount is not the total
+    TotalMismatch {
+        expected: Money,
+        total: Money,
+        difference: Money,
+    },
+    /// the line items do not add up to the subtotal
+    LineItemsMismatch {
+        check: LineSumCheck,
+    },
+    DueBeforeInvoice {
+        inv_date: NaiveDate,
+        due_date: NaiveDate,
+    },
+    FutureDate {
+        inv_date: NaiveDate,
+    },
+    /// older than the maximum age of the validator
+    OldDate {
+        inv_date: NaiveDate,
+        days: i64,
+    },
+    /// the number does not look like the invoices of the vendor
+    InvoiceNumber {
+        inv_no: String,
+        pattern: String,
+    },
+    /// the amount is not in the currency of the total
+    CurrencyMismatch {
+        field: String,
+        expected: Currency,
+        found: Currency,
+    },
+}
+
+/// Checks invoices for amounts that do not add up and unlikely dates
+#[derive(Debug, Clone)]
+pub struct Validator {
+    /// differences up to this amount are rounding
+    pub tolerance: Decimal,
+    /// invoices older than this are flagged
+    pub max_age_days: i64,
+    /// the local date if `None`
+    pub today: Option<NaiveDate>,
+    /// known invoice numbers by vendor, the vendor is
+    /// found if its name contains the key ignoring case
+    pub inv_no_patterns: Vec<(String, Regex)>,
+}
+
+impl Default for Validator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self {
+            tolerance: Decimal::new(1, 2),
+            max_age_days: 365 * 2,
+            today: None,
+            inv_no_patterns: Vec::new(),
+        }
+    }
+
+    pub fn tolerance(mut self, tolerance: Decimal) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn max_age_days(mut self, days: i64) -> Self {
+        self.max_age_days = days;
+        self
+    }
+
+    pub fn today(mut self, today: NaiveDate) -> Self {
+        self.today = Some(today);
+        self
+    }
+
+    /// invoice numbers of the vendor must match the regex entirely, i.e. `INV-\d{6}`
+    pub fn inv_no_pattern(mut self, vendor: &str, pattern: &str) -> OcrResult<Self> {
+        let re = RegexBuilder::new(&format!("^(?:{pattern})$"))
+            .case_insensitive(true)
+            .build()?;
+        self.inv_no_patterns.push((vendor.to_lowercase(), re));
+        Ok(self)
+    }
+
+    pub fn validate(&self, details: &InvoiceDetails) -> Vec<ValidationWarning> {
+        use ValidationWarning as W;
+        let mut warnings = Vec::new();
+
+        let inv_no = details.inv_no.as_deref().filter(|n| !n.trim().is_empty());
+        let inv_date = details.inv_date.as_ref().left().copied();
+        let due_date = details.due_date.as_ref().left().copied();
+        let total = details.total.as_ref().left().copied();
+        for (field, missing) in [
+            ("inv_no", inv_no.is_none()),
+            ("inv_date", inv_date.is_none()),
+            ("total", total.is_none()),
+        ] {
+            if missing {
+                warnings.push(W::Missing { field });
+            }
+        }
+
+        let mismatched = currency_mismatches(details);
+        let consistent = mismatched.is_empty();
+        warnings.extend(mismatched);
+
+        if let (Some(total), Some(subtotal), true) = (total, details.subtotal, consistent) {
+            let zero = Money::zero(total.currency);
+            let expected = subtotal
+                .checked_add(details.tax.unwrap_or(zero))
+                .and_then(|m| m.checked_add(details.shipping.unwrap_or(zero)))
+                .and_then(|m| m.checked_sub(details.discount.unwrap_or(zero)));
+            if let Some(expected) = expected
+                && let Some(difference) = total.checked_sub(expected)
+                && difference.amount.abs() > self.tolerance
+            {
+                warnings.push(W::TotalMismatch {
+                    expected,
+                    total,
+                    difference,
+                });
+            }
+        }
+
+        // without a subtotal the lines are compared with the total, which includes the tax
+        if let Some(check) = details.line_sum_check()
+            && consistent
+            && details.subtotal.is_some()
+            && !check.is_ok(self.tolerance)
+        {
+            warnings.push(W::LineItemsMismatch { check });
+        }
+
+        if let (Some(inv_date), Some(due_date)) = (inv_date, due_date)
+            && due_date < inv_date
+        {
+            warnings.push(W::DueBeforeInvoice { inv_date, due_date });
+        }
+
+        if let Some(inv_date) = inv_date {
+            let today = self.today.unwrap_or_else(|| Local::now().date_naive());
+            let days = (today - inv_date).num_days();
+            if days < 0 {
+                warnings.push(W::FutureDate { inv_date });
+            } else if days > self.max_age_days {
+                warnings.push(W::OldDate { inv_date, days });
+            }
+        }
+
+        let vendor = details.vendor.as_deref().unwrap_or_default().to_lowercase();
+        let pattern = self
+            .inv_no_patterns
+            .iter()
+            .find(|(key, _)| !vendor.is_empty() && vendor.contains(key.as_str()));
+        if let (Some(inv_no), Some((_, re))) = (inv_no, pattern)
+            && !re.is_match(inv_no.trim())
+        {
+            warnings.push(W::InvoiceNumber {
+                inv_no: inv_no.to_owned(),
+                pattern: re.as_str().to_owned(),
+            });
+        }
+
+        warnings
+    }
+}
+
+/// amounts in another currency than the total, or the first amount if the total was not read
+fn currency_mismatches(details: &InvoiceDetails) -> Vec<ValidationWarning> {
+    let mut amounts: Vec<(String, Money)> = [
+        ("total", details.total.as_ref().left().copied()),
+        ("subtotal", details.subtotal),
+        ("tax", details.tax),
+        ("shipping", details.shipping),
+        ("discount", details.discount),
+    ]
+    .into_iter()
+    .filter_map(|(field, m)| Some((field.to_owned(), m?)))
+    .collect();
+
+    for tax in &details.taxes {
+        amounts.push((format!("taxes[{}]", tax.kind), tax.amount));
+    }
+    for (i, item) in details.line_items.iter().enumerate() {
+        amounts.extend(
+            item.unit_price
+                .map(|m| (format!("line_items[{i}].unit_price"), m)),
+        );
+        amounts.extend(item.amount.map(|m| (format!("line_items[{i}].amount"), m)));
+    }
+
+    let Some(expected) = amounts.first().map(|(_, m)| m.currency) else {
+        return Vec::new();
+    };
+    amounts
+        .into_iter()
+        .filter(|(_, m)| m.currency != expected)
+        .map(|(field, m)| ValidationWarning::CurrencyMismatch {
+            field,
+            expected,
+            found: m.currency,
+        })
+        .collect()
+}
+
+impl InvoiceDetails {
+    /// warnings of the default validator, empty if nothing looks wrong
+    pub fn validate(&self) -> Vec<ValidationWarning> {
+        Validator::default().validate(self)
+    }
+}
+
+#[test]
+fn validate() {
+    let res: super::InvoiceResponse = serde_json::from_value(serde_json::json!({
+        "invoice_no": "1042", "vendor": "Golden Waffles Inc", "acct_no": null,
+        "inv_date": "01/05/2024", "due_date": "12/20/2023",
+        "total": "$112.00", "subtotal": "$100.00", "tax": "$8.00", "shipping": "$5.00",
+        "discount": "(1.00)",
+    }))
+    .unwrap();
+    let mut details = InvoiceDetails::from(res);
+    let today = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+    let validator = Validator::new().today(today);
+    assert_eq!(
+        validator.validate(&details),
+        vec![ValidationWarning::DueBeforeInvoice {
+            inv_date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+            due_date: NaiveDate::from_ymd_opt(2023, 12, 20).unwrap(),
+        }]
+    );
+
+    details.due_date = either::Either::Right("Net 30".into());
+    details.tax = Money::parse("€8.00");
+    let validator = validator
+        .max_age_days(10)
+        .inv_no_pattern("golden waffles", r"GW-\d+")
+        .unwrap();
+    let kinds: Vec<String> = validator
+        .validate(&details)
+        .iter()
+        .map(|w| {
+            serde_json::to_value(w).unwrap()["kind"]
+                .as_str()
+                .unwrap()
+                .to_owned()
+        })
+        .collect();
+    assert_eq!(kinds, ["currency_mismatch", "old_date", "invoice_number"]);
+}
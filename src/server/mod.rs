@@ -2,12 +2,15 @@
 /// for making requests to server
 /// whcih is written in python is an independent
 /// codebase
+pub mod confidence;
 pub mod docling;
 pub mod invoice;
-use crate::{OCRServerErr, err::OcrResult};
+pub mod options;
+use crate::{OCRServerErr, date::DateParser, err::OcrResult, lang::Language};
 use docling::{OcrDoc, ParsedDoc};
 use image::DynamicImage;
 use invoice::{InvoiceDetails, InvoiceResponse};
+use options::DoclingOptions;
 use reqwest::{
     Client, Url,
     multipart::{Form, Part},
@@ -34,10 +37,64 @@ impl OcrClient {
         Ok(res.into())
     }
 
+    /// makes a request to /.../ocr/invoice with a named document.
+    /// the languages of the document are sent as `lang` fields,
+    /// numeric dates are read in the order of the first one i.e. `05/01/2024`
+    /// is the 5th of January in Spanish
+    pub async fn invoice_doc(&self, doc: OcrDoc) -> OcrResult<InvoiceDetails> {
+        let dates = doc
+            .lang
+            .first()
+            .map_or_else(DateParser::default, |l| DateParser::for_language(*l));
+        let mime = doc.mime();
+        let fields = doc
+            .lang
+            .iter()
+            .map(|l| ("lang".to_owned(), l.iso639_1().to_owned()))
+            .collect();
+        let res = self
+            .bytes_req::<InvoiceResponse>("ocr/invoice", doc.bytes, doc.name, Some(mime), fields)
+            .await?;
+        Ok(InvoiceDetails::from_response(res, &dates))
+    }
+
+    /// makes a request to /.../ocr/invoice with language hints
+    pub async fn invoice_with_lang(
+        &self,
+        img: &DynamicImage,
+        langs: &[Language],
+    ) -> OcrResult<InvoiceDetails> {
+        let doc = OcrDoc::from_img(img)?.with_lang(langs.iter().copied());
+        self.invoice_doc(doc).await
+    }
+
     /// makes a request to /.../ocr/doc
-    pub async fn docling(&self, doc: OcrDoc<'_>) -> OcrResult<ParsedDoc> {
+    /// the languages of the document, if any, are sent as `ocr_lang`
+    pub async fn docling(&self, doc: OcrDoc) -> OcrResult<ParsedDoc> {
+        let mime = doc.mime();
+        let engine = DoclingOptions::default().ocr_engine;
+        let fields = doc
+            .lang
+            .iter()
+            .map(|l| ("ocr_lang".to_owned(), l.code_for(engine).to_owned()))
+            .collect();
         let res = self
-            .bytes_req("ocr/doc", doc.bytes, doc.name.into())
+            .bytes_req("ocr/doc", doc.bytes, doc.name, Some(mime), fields)
+            .await?;
+        Ok(res)
+    }
+
+    /// makes a request to /.../ocr/doc with the given conversion options.
+    /// the languages of the document are used if the options have none
+    pub async fn docling_with(&self, doc: OcrDoc, opts: &DoclingOptions) -> OcrResult<ParsedDoc> {
+        let mime = doc.mime();
+        let fields = if opts.ocr_lang.is_empty() && !doc.lang.is_empty() {
+            opts.clone().ocr_lang(doc.lang).form_fields()?
+        } else {
+            opts.form_fields()?
+        };
+        let res = self
+            .bytes_req("ocr/doc", doc.bytes, doc.name, Some(mime), fields)
             .await?;
         Ok(res)
     }
@@ -50,21 +107,42 @@ impl OcrClient {
         let mut img_bytes = Vec::new();
 
         img.write_to(&mut Cursor::new(&mut img_bytes), image::ImageFormat::Png)?;
-        self.bytes_req(url_path, img_bytes, "[Unknown].png".into())
-            .await
+        self.bytes_req(
+            url_path,
+            img_bytes,
+            "[Unknown].png".into(),
+            Some("image/png"),
+            Vec::new(),
+        )
+        .await
     }
 
     /// makes a request to given path
     /// the path should not include the base
-    async fn bytes_req<T>(&self, url_path: &str, data: Vec<u8>, name: String) -> OcrResult<T>
+    /// `fields` are sent as text fields alongside the file
+    async fn bytes_req<T>(
+        &self,
+        url_path: &str,
+        data: Vec<u8>,
+        name: String,
+        mime: Option<&str>,
+        fields: Vec<(String, String)>,
+    ) -> OcrResult<T>
     where
         T: for<'a> Deserialize<'a>,
     {
         // NOTE: filename has to be attached otherwise it causes
         // issue on the server side
-        let part = Part::bytes(data).file_name(name);
+        let mut part = Part::bytes(data).file_name(name);
+        if let Some(mime) = mime {
+            part = part.mime_str(mime)?;
+        }
 
-        let form = Form::new().part("file", part);
+        let form = fields
+            .into_iter()
+            .fold(Form::new().part("file", part), |form, (k, v)| {
+                form.text(k, v)
+            });
 
         let req = self.client.post(self.base.join(url_path)?).multipart(form);
 
@@ -0,0 +1,201 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{err::OcrResult, lang::Language};
+
+/// OCR engine used by Docling
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrBackend {
+    #[default]
+    #[serde(rename = "easyocr")]
+    EasyOcr,
+    Tesseract,
+    #[serde(rename = "tesserocr")]
+    TesserOcr,
+    #[serde(rename = "rapidocr")]
+    RapidOcr,
+    #[serde(rename = "ocrmac")]
+    OcrMac,
+}
+
+/// Backend used to parse born-digital PDFs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PdfBackend {
+    Pypdfium2,
+    DlparseV1,
+    DlparseV2,
+    #[default]
+    DlparseV4,
+}
+
+/// Accuracy of the table structure recognition
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TableMode {
+    Fast,
+    #[default]
+    Accurate,
+}
+
+/// How pictures found in the document are returned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageExportMode {
+    /// only a placeholder is returned
+    Placeholder,
+    /// images are embedded as base64
+    #[default]
+    Embedded,
+    /// images are returned as references
+    Referenced,
+}
+
+/// Conversion options sent alongside the document.
+/// The defaults match the defaults of the server
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DoclingOptions {
+    /// perform OCR on bitmap content
+    pub do_ocr: bool,
+    /// replace the text layer of born-digital PDFs with OCR
+    pub force_ocr: bool,
+    pub ocr_engine: OcrBackend,
+    /// languages of the document, the server assumes english if empty.
+    /// sent using the codes of `Self::ocr_engine`
+    #[serde(skip)]
+    pub ocr_lang: Vec<Language>,
+    pub pdf_backend: PdfBackend,
+    /// recognise the structure of tables
+    pub do_table_structure: bool,
+    pub table_mode: TableMode,
+    /// 1 based, inclusive range of pages to convert, all pages if `None`
+    pub page_range: Option<(usize, usize)>,
+    /// return the images of the pictures found in the document
+    pub include_images: bool,
+    /// scale of the returned images
+    pub images_scale: f64,
+    pub image_export_mode: ImageExportMode,
+    /// fail the whole conversion if a page fails
+    pub abort_on_error: bool,
+}
+
+impl Default for DoclingOptions {
+    fn default() -> Self {
+        Self {
+            do_ocr: true,
+            force_ocr: false,
+            ocr_engine: OcrBackend::default(),
+            ocr_lang: Vec::new(),
+            pdf_backend: PdfBackend::default(),
+            do_table_structure: true,
+            table_mode: TableMode::default(),
+            page_range: None,
+            include_images: true,
+            images_scale: 2.0,
+            image_export_mode: ImageExportMode::default(),
+            abort_on_error: false,
+        }
+    }
+}
+
+impl DoclingOptions {
+    pub fn force_ocr(mut self, force_ocr: bool) -> Self {
+        self.force_ocr = force_ocr;
+        self
+    }
+
+    pub fn ocr_engine(mut self, engine: OcrBackend) -> Self {
+        self.ocr_engine = engine;
+        self
+    }
+
+    pub fn ocr_lang<I: IntoIterator<Item = Language>>(mut self, langs: I) -> Self {
+        self.ocr_lang = langs.into_iter().collect();
+        self
+    }
+
+    pub fn table_structure(mut self, enabled: bool) -> Self {
+        self.do_table_structure = enabled;
+        self
+    }
+
+    pub fn table_mode(mut self, mode: TableMode) -> Self {
+        self.table_mode = mode;
+        self
+    }
+
+    /// 1 based, inclusive range of pages
+    pub fn page_range(mut self, start: usize, end: usize) -> Self {
+        self.page_range = Some((start, end));
+        self
+    }
+
+    pub fn include_images(mut self, include: bool) -> Self {
+        self.include_images = include;
+        self
+    }
+
+    /// the options as multipart form fields,
+    /// lists are sent as repeated fields and `None` values are left out
+    pub fn form_fields(&self) -> OcrResult<Vec<(String, String)>> {
+        let mut fields = Vec::new();
+
+        let Value::Object(map) = serde_json::to_value(self)? else {
+            return Ok(fields);
+        };
+
+        for (key, value) in map {
+            match value {
+                Value::Null => {}
+                Value::Array(values) => {
+                    fields.extend(values.into_iter().map(|v| (key.clone(), field_value(v))))
+                }
+                v => fields.push((key, field_value(v))),
+            }
+        }
+
+        fields.extend(self.ocr_lang.iter().map(|l| {
+            (
+                "ocr_lang".to_owned(),
+                l.code_for(self.ocr_engine).to_owned(),
+            )
+        }));
+
+        Ok(fields)
+    }
+}
+
+fn field_value(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        v => v.to_string(),
+    }
+}
+
+#[test]
+fn fields() {
+    let opts = DoclingOptions::default()
+        .force_ocr(true)
+        .ocr_engine(OcrBackend::Tesseract)
+        .ocr_lang([Language::Spanish, Language::English])
+        .page_range(1, 3);
+    let fields = opts.form_fields().unwrap();
+    let get = |key: &str| -> Vec<&str> {
+        fields
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .collect()
+    };
+
+    assert_eq!(get("force_ocr"), vec!["true"]);
+    assert_eq!(get("ocr_engine"), vec!["tesseract"]);
+    assert_eq!(get("page_range"), vec!["1", "3"]);
+    assert_eq!(get("ocr_lang"), vec!["spa", "eng"]);
+    assert_eq!(get("table_mode"), vec!["accurate"]);
+    assert_eq!(get("pdf_backend"), vec!["dlparse_v4"]);
+
+    let fields = DoclingOptions::default().form_fields().unwrap();
+    assert!(!fields.iter().any(|(k, _)| k == "page_range"));
+}
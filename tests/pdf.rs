@@ -1 +1,379 @@
+use std::sync::{Arc, Mutex};
 
+use image::{DynamicImage, Rgba};
+use ocr_client::{
+    lang::Language,
+    money::Decimal,
+    pdf::PdfEngine,
+    pii::{PiiKind, PiiSource, Risk},
+    redact::{RedactPattern, RedactSource, Redactor},
+    search::fuzzy::{FuzzyOptions, HitSource},
+    server::{
+        OcrClient,
+        docling::{OcrDoc, ParsedDoc},
+        invoice::InvoiceDetails,
+    },
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+fn golden_waffles() -> Vec<u8> {
+    std::fs::read("./tests/golden_waffles.pdf").unwrap()
+}
+
+#[test]
+fn redact_golden_waffles() {
+    let mut doc = PdfEngine::new().doc(golden_waffles()).unwrap();
+    // a single 200x139 logo in a 612x792pt page
+    assert_eq!(doc.imgs.len(), 1);
+
+    // the logo is ocred, the box is in pixels of the logo
+    let ocred: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [
+            { "text": "4111 1111 1111 1111", "prov": [{ "page_no": 1, "charspan": [0, 19], "bbox": { "l": 20.0, "t": 60.0, "r": 180.0, "b": 80.0, "coord_origin": "TOPLEFT" } }] },
+        ],
+    }))
+    .unwrap();
+    doc.parsed_doc = vec![Ok(ocred)];
+
+    let redactor = Redactor::new([RedactPattern::Pan, RedactPattern::Phone]).unwrap();
+    let redacted = doc.redact(&redactor).unwrap();
+
+    let sources: Vec<_> = redacted.audit.iter().map(|r| r.source).collect();
+    assert!(sources.contains(&RedactSource::PdfImage {
+        img_idx: 0,
+        text_idx: 0
+    }));
+    // "(888) 596-4040" of the text layer
+    assert!(sources.contains(&RedactSource::TextLayer));
+    assert!(redacted.audit.iter().all(|r| r.page_no == 1));
+
+    let out = PdfEngine::new().doc(redacted.bytes).unwrap();
+    let page = out.render_page(0).unwrap().to_rgba8();
+    let scale = page.width() as f64 / 612.0;
+    let black = Rgba([0, 0, 0, 255]);
+
+    // the logo covers (26.16, 32.16) - (170.76, 132.66) from the top left
+    // of the page, the card number sits at 10% - 90% across and 43% - 58% down
+    let (x, y) = (26.16 + 144.6 * 0.5, 32.16 + 100.5 * 0.5);
+    let px = |v: f64| (v * scale) as u32;
+    assert_eq!(*page.get_pixel(px(x), px(y)), black);
+
+    for r in &redacted.audit {
+        let rect = r.rect.unwrap();
+        let center = page.get_pixel((rect.x0 + rect.x1) / 2, (rect.y0 + rect.y1) / 2);
+        assert_eq!(*center, black, "{r:?}");
+    }
+
+    // the phone number sits at (292.4, 103.1) - (360, 115) from the top left
+    let phone = redacted
+        .audit
+        .iter()
+        .find(|r| r.source == RedactSource::TextLayer)
+        .and_then(|r| r.rect)
+        .unwrap();
+    assert!(phone.x0 <= px(293.0) && phone.y0 <= px(108.0) && phone.y1 >= px(110.0));
+}
+
+#[test]
+fn redact_pii_golden_waffles() {
+    let mut doc = PdfEngine::new().doc(golden_waffles()).unwrap();
+    let ocred: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [
+            { "text": "Card 4111 1111 1111 1111", "prov": [{ "page_no": 1, "charspan": [0, 24], "bbox": { "l": 20.0, "t": 60.0, "r": 180.0, "b": 80.0, "coord_origin": "TOPLEFT" } }] },
+        ],
+    }))
+    .unwrap();
+    doc.parsed_doc = vec![Ok(ocred)];
+
+    let report = doc.detect_pii().unwrap();
+    let card = report
+        .findings
+        .iter()
+        .find(|f| f.kind == PiiKind::CardNumber)
+        .unwrap();
+    assert_eq!(
+        card.source,
+        PiiSource::PdfImage {
+            img_idx: 0,
+            text_idx: 0
+        }
+    );
+    assert_eq!(card.page_no, Some(1));
+
+    let redactor = Redactor::new([]).unwrap();
+    let redactions = report.pdf_redactions(Risk::Critical);
+    let redacted = redactor.redact_pdf(&doc, &redactions).unwrap();
+    assert_eq!(redacted.audit.len(), 1);
+    assert!(redacted.audit[0].rect.is_some());
+}
+
+#[test]
+fn redact_fuzzy_hits_golden_waffles() {
+    let mut doc = PdfEngine::new().doc(golden_waffles()).unwrap();
+    let ocred: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [
+            { "text": "Waffle Logo 7731", "prov": [{ "page_no": 1, "charspan": [0, 16], "bbox": { "l": 20.0, "t": 60.0, "r": 180.0, "b": 80.0, "coord_origin": "TOPLEFT" } }] },
+        ],
+    }))
+    .unwrap();
+    doc.parsed_doc = vec![Ok(ocred)];
+
+    let opts = FuzzyOptions::default();
+    let hits: Vec<_> = ["(888) 596-4040", "Logo 7731"]
+        .iter()
+        .filter_map(|needle| doc.fuzzy_search(needle, &opts).into_iter().next())
+        .map(|(source, hit)| (source, hit.text_idx, hit.range))
+        .collect();
+    assert_eq!(hits.len(), 2);
+
+    let redactions = doc.redactions("search", hits).unwrap();
+    let sources: Vec<_> = redactions.iter().map(|r| r.source).collect();
+    assert!(sources.contains(&RedactSource::TextLayer));
+    assert!(sources.contains(&RedactSource::PdfImage {
+        img_idx: 0,
+        text_idx: 0
+    }));
+
+    let redacted = Redactor::new([])
+        .unwrap()
+        .redact_pdf(&doc, &redactions)
+        .unwrap();
+    assert!(redacted.audit.iter().all(|r| r.page_no == 1));
+    assert!(redacted.audit.len() >= 2);
+
+    // a hit outside of the document is an error, not a silent gap
+    let lost = [(HitSource::Image(0), 9, 0..4)];
+    assert!(doc.redactions("search", lost).is_err());
+}
+
+#[test]
+fn redact_needs_ocr() {
+    // the logo was never ocred, its content is unknown
+    let doc = PdfEngine::new().doc(golden_waffles()).unwrap();
+    let redactor = Redactor::new([RedactPattern::Pan]).unwrap();
+    assert!(matches!(
+        doc.redact(&redactor),
+        Err(ocr_client::OcrErrs::NotOcred(0))
+    ));
+}
+
+#[test]
+fn searchable_golden_waffles() {
+    let mut doc = PdfEngine::new().doc(golden_waffles()).unwrap();
+    let ocred: ParsedDoc = serde_json::from_value(serde_json::json!({
+        "texts": [
+            { "text": "Waffle Logo 7731", "prov": [{ "page_no": 1, "charspan": [0, 16], "bbox": { "l": 20.0, "t": 60.0, "r": 180.0, "b": 80.0, "coord_origin": "TOPLEFT" } }] },
+        ],
+    }))
+    .unwrap();
+    doc.parsed_doc = vec![Ok(ocred)];
+
+    let bytes = doc.searchable().unwrap();
+    let out = PdfEngine::new().doc(bytes).unwrap();
+
+    let text = out
+        .load()
+        .unwrap()
+        .pages()
+        .get(0)
+        .unwrap()
+        .text()
+        .unwrap()
+        .all();
+    assert!(text.contains("Waffle Logo 7731"), "{text}");
+    // the original text layer is kept
+    assert!(text.contains("Golden Malted"));
+    assert!(out.contains("Logo 7731"));
+}
+
+/// answers the paths of `routes` with their json and anything else with an error,
+/// returns the address and the paths that were requested
+async fn mock_server(routes: &[(&str, serde_json::Value)]) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = format!("http://{}", listener.local_addr().unwrap());
+    let paths = Arc::new(Mutex::new(Vec::new()));
+    let routes: Vec<_> = routes
+        .iter()
+        .map(|(path, json)| (path.to_string(), json.to_string()))
+        .collect();
+
+    let hits = paths.clone();
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                return;
+            };
+
+            let mut req = Vec::new();
+            let mut buf = [0; 8192];
+            let head_end = loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                req.extend_from_slice(&buf[..n]);
+                if let Some(idx) = req.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break idx + 4;
+                }
+            };
+            let head = String::from_utf8_lossy(&req[..head_end]).to_lowercase();
+            let len = head
+                .lines()
+                .find_map(|l| l.strip_prefix("content-length:"))
+                .and_then(|l| l.trim().parse::<usize>().ok());
+            while match len {
+                Some(len) => req.len() < head_end + len,
+                None => !req.ends_with(b"0\r\n\r\n"),
+            } {
+                let n = stream.read(&mut buf).await.unwrap();
+                req.extend_from_slice(&buf[..n]);
+            }
+
+            let path = head
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .to_owned();
+            let (status, body) = match routes.iter().find(|(p, _)| *p == path) {
+                Some((_, json)) => ("200 OK", json.clone()),
+                None => (
+                    "500 Internal Server Error",
+                    r#"{"error":"not mocked"}"#.into(),
+                ),
+            };
+            hits.lock().unwrap().push(path);
+
+            let res = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(res.as_bytes()).await.unwrap();
+        }
+    });
+
+    (addr, paths)
+}
+
+/// the invoice of golden_waffles.pdf as read by the server, without line items
+fn golden_waffles_invoice() -> serde_json::Value {
+    serde_json::json!({
+        "invoice_no": "FCHRG205999", "vendor": "Golden Malted", "acct_no": null,
+        "inv_date": "11/20/2023", "due_date": null, "total": "$162.50", "subtotal": "$162.50"
+    })
+}
+
+/// a Docling document holding the line item table of golden_waffles.pdf
+fn line_item_table() -> serde_json::Value {
+    let cell = |text: &str, row: usize, col: usize| {
+        serde_json::json!({
+            "text": text,
+            "start_row_offset_idx": row, "end_row_offset_idx": row + 1,
+            "start_col_offset_idx": col, "end_col_offset_idx": col + 1,
+            "column_header": row == 0,
+        })
+    };
+    let rows = [
+        ["Description", "Qty", "Amount"],
+        ["Waffle mix 5lb", "4", "$130.00"],
+        ["Malted syrup", "1", "$32.50"],
+    ];
+    let cells: Vec<_> = rows
+        .iter()
+        .enumerate()
+        .flat_map(|(r, row)| row.iter().enumerate().map(move |(c, t)| cell(t, r, c)))
+        .collect();
+    serde_json::json!({
+        "texts": [],
+        "tables": [{ "prov": [], "data": { "table_cells": cells, "num_rows": 3, "num_cols": 3 } }]
+    })
+}
+
+fn descriptions(details: &InvoiceDetails) -> Vec<&str> {
+    details
+        .line_items
+        .iter()
+        .map(|l| l.description.as_str())
+        .collect()
+}
+
+#[tokio::test]
+async fn invoice_line_items_from_ocred_tables() {
+    let (addr, paths) = mock_server(&[("/ocr/invoice", golden_waffles_invoice())]).await;
+    let client = OcrClient::new(addr).unwrap();
+
+    let mut doc = PdfEngine::new().doc(golden_waffles()).unwrap();
+    doc.parsed_doc = vec![Ok(serde_json::from_value(line_item_table()).unwrap())];
+
+    let invoice = doc.into_invoice_doc(&client).await;
+    let details = invoice.invoice_details.unwrap();
+    assert_eq!(descriptions(&details), ["Waffle mix 5lb", "Malted syrup"]);
+    assert!(details.line_sum_check().unwrap().is_ok(Decimal::ZERO));
+    // the images were already ocred and hold the table
+    assert_eq!(*paths.lock().unwrap(), ["/ocr/invoice"]);
+}
+
+#[tokio::test]
+async fn invoice_line_items_from_docling() {
+    let (addr, paths) = mock_server(&[
+        ("/ocr/invoice", golden_waffles_invoice()),
+        ("/ocr/doc", line_item_table()),
+    ])
+    .await;
+    let client = OcrClient::new(addr).unwrap();
+
+    // a single request unless asked for the line items
+    let doc = PdfEngine::new().doc(golden_waffles()).unwrap();
+    let invoice = doc.into_invoice_doc(&client).await;
+    assert!(invoice.invoice_details.unwrap().line_items.is_empty());
+    assert!(invoice.docling.is_none());
+    assert_eq!(*paths.lock().unwrap(), ["/ocr/invoice"]);
+    paths.lock().unwrap().clear();
+
+    // the logo was ocred and holds no table, the whole pdf goes thru Docling
+    let mut doc = PdfEngine::new().doc(golden_waffles()).unwrap();
+    doc.parsed_doc = vec![Ok(serde_json::from_value(
+        serde_json::json!({ "texts": [] }),
+    )
+    .unwrap())];
+    let invoice = doc.into_invoice_doc_with_lines(&client).await;
+    let details = invoice.invoice_details.unwrap();
+    assert_eq!(descriptions(&details), ["Waffle mix 5lb", "Malted syrup"]);
+    assert!(matches!(invoice.docling, Some(Ok(_))));
+    assert_eq!(*paths.lock().unwrap(), ["/ocr/invoice", "/ocr/doc"]);
+}
+
+#[tokio::test]
+async fn invoice_line_items_docling_error() {
+    let (addr, _) = mock_server(&[("/ocr/invoice", golden_waffles_invoice())]).await;
+    let client = OcrClient::new(addr).unwrap();
+
+    let mut doc = PdfEngine::new().doc(golden_waffles()).unwrap();
+    doc.parsed_doc = vec![Ok(serde_json::from_value(
+        serde_json::json!({ "texts": [] }),
+    )
+    .unwrap())];
+    let invoice = doc.into_invoice_doc_with_lines(&client).await;
+    // the invoice is kept, the error of Docling with it
+    assert!(invoice.invoice_details.unwrap().line_items.is_empty());
+    assert!(matches!(invoice.docling, Some(Err(_))));
+}
+
+#[tokio::test]
+async fn invoice_dates_in_the_language_of_the_doc() {
+    let mut invoice = golden_waffles_invoice();
+    invoice["inv_date"] = "05/01/2024".into();
+    let (addr, _) = mock_server(&[("/ocr/invoice", invoice)]).await;
+    let client = OcrClient::new(addr).unwrap();
+    let img = DynamicImage::new_rgb8(8, 8);
+    let date = |m, d| chrono::NaiveDate::from_ymd_opt(2024, m, d);
+
+    // month first without a language
+    let details = client.invoice(&img).await.unwrap();
+    assert_eq!(details.inv_date.left(), date(5, 1));
+
+    let doc = OcrDoc::from_img(&img)
+        .unwrap()
+        .with_lang([Language::Spanish]);
+    let details = client.invoice_doc(doc).await.unwrap();
+    assert_eq!(details.inv_date.left(), date(1, 5));
+}